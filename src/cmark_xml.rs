@@ -2,57 +2,129 @@
 //!
 //! Convert CommonMark <=> XML
 //!
+use crate::error::{Error, Result};
 
 /// XML namespace
 const NS: &str = "markdown";
 
+/// Format of frontmatter, distinguished by delimiter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrontmatterFormat {
+    /// Delimited by +++
+    Toml,
+    /// Delimited by ---
+    Yaml,
+    /// JSON object at the top of file, without delimiter
+    Json,
+}
+
+impl FrontmatterFormat {
+    /// Delimiter line of frontmatter, None for JSON
+    pub fn delimiter(self) -> Option<&'static str> {
+        match self {
+            Self::Toml => Some("+++"),
+            Self::Yaml => Some("---"),
+            Self::Json => None,
+        }
+    }
+}
+
 /// Read CommonMark with frontmatter
 ///
 /// Returns tuple, (CommonMark body, frontmatter)
 pub fn read_cmark_with_frontmatter<R: std::io::Read>(
     reader: &mut R,
-) -> std::io::Result<(String, Option<String>)> {
+) -> Result<(String, Option<String>)> {
+    let (cmark_body, frontmatter) = read_cmark_with_frontmatter_format(reader)?;
+    Ok((cmark_body, frontmatter.map(|(_, frontmatter)| frontmatter)))
+}
+
+/// Read CommonMark with frontmatter and its format
+///
+/// Returns tuple, (CommonMark body, (frontmatter format, frontmatter))
+pub fn read_cmark_with_frontmatter_format<R: std::io::Read>(
+    reader: &mut R,
+) -> Result<(String, Option<(FrontmatterFormat, String)>)> {
     let mut buf = String::new();
     reader.read_to_string(&mut buf)?;
 
-    if buf.starts_with("+++") {
+    let first_line = buf.lines().next().map(str::trim_end);
+    if first_line == Some("+++") {
         // TOML frontmatter
-        split_frontmatter(&buf, "+++")
-    } else if buf.starts_with("---") {
+        split_frontmatter(&buf, FrontmatterFormat::Toml)
+    } else if first_line == Some("---") {
         // YAML frontmatter
-        split_frontmatter(&buf, "---")
+        split_frontmatter(&buf, FrontmatterFormat::Yaml)
+    } else if let Some(json_len) = json_object_len(&buf) {
+        // JSON frontmatter, followed by line breaks kept as they are
+        let json_len = json_len + buf[json_len..].len()
+            - buf[json_len..].trim_start_matches(['\r', '\n']).len();
+        let (frontmatter, cmark_body) = buf.split_at(json_len);
+        Ok((
+            cmark_body.to_string(),
+            Some((FrontmatterFormat::Json, frontmatter.to_string())),
+        ))
     } else {
         // No frontmatter, only CommonMark body
         Ok((buf, None))
     }
 }
 
+/// Length of JSON object at the top of text
+///
+/// None if text does not start with JSON object, e.g. Hugo shortcode {{< ... >}}.
+fn json_object_len(text: &str) -> Option<usize> {
+    if !text.starts_with('{') {
+        return None;
+    }
+    let mut stream = serde_json::Deserializer::from_str(text).into_iter::<serde_json::Value>();
+    match stream.next() {
+        Some(Ok(serde_json::Value::Object(_))) => Some(stream.byte_offset()),
+        _ => None,
+    }
+}
+
 /// Split frontmatter and CommonMark body
-fn split_frontmatter(filebody: &str, delimiter: &str) -> std::io::Result<(String, Option<String>)> {
-    let mut iter = filebody.splitn(3, delimiter);
-    let _ = iter.next(); // should empty
-    let frontmatter = iter.next();
-    let cmark_body = iter.next();
-
-    if let (Some(frontmatter), Some(cmark_body)) = (frontmatter, cmark_body) {
-        Ok((cmark_body.to_string(), Some(frontmatter.to_string())))
-    } else {
-        // second delimiter can not be found.
-        Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))
+///
+/// Frontmatter is closed by delimiter line, or "..." line for YAML.
+fn split_frontmatter(
+    filebody: &str,
+    format: FrontmatterFormat,
+) -> Result<(String, Option<(FrontmatterFormat, String)>)> {
+    let delimiter = format.delimiter().unwrap_or_default();
+    let mut lines = filebody.split_inclusive('\n');
+    // Line break after opening delimiter is not a part of frontmatter
+    let start = lines.next().map_or(0, str::len);
+    let mut end = start;
+    for line in lines {
+        let line_text = line.trim_end();
+        if line_text == delimiter || (format == FrontmatterFormat::Yaml && line_text == "...") {
+            return Ok((
+                filebody[end + line.len()..].to_string(),
+                Some((format, filebody[start..end].to_string())),
+            ));
+        }
+        end += line.len();
     }
+
+    // second delimiter can not be found.
+    Err(Error::Frontmatter(format!(
+        "Closing {} of frontmatter NOT found",
+        delimiter
+    )))
 }
 
 /// Convert CommonMark text to XML string
 ///
 /// If CommonMark text contains Jinja style shortcode {{ ... }} used in Hugo, Zora, etc.,
 /// set escape_shortcode to true.
-pub fn xml_from_cmark(cmark_text: &str, escape_shortcode: bool) -> String {
+pub fn xml_from_cmark(cmark_text: &str, escape_shortcode: bool) -> Result<String> {
     let mut buf = Vec::<u8>::new();
 
     let xml_root = xmldom_from_cmark(cmark_text, escape_shortcode);
-    xml_root.write_to(&mut buf).unwrap();
+    xml_root.write_to(&mut buf)?;
 
-    String::from_utf8(buf).unwrap()
+    Ok(String::from_utf8_lossy(&buf).into_owned())
 }
 
 /// Convert CommonMark text to XML DOM
@@ -63,17 +135,19 @@ pub fn xmldom_from_cmark(cmark_text: &str, escape_shortcode: bool) -> minidom::E
     // parse body as comrak AST
     let arena = comrak::Arena::new();
 
-    let ast_root = if escape_shortcode {
+    let cmark_text = if escape_shortcode {
         // pre-process shortcodes
-        let escaped = escape_all_shortcodes(&cmark_text);
+        let escaped = escape_all_shortcodes(cmark_text);
         log::trace!("escape_shortcode: {:?}\n", escaped);
-        comrak::parse_document(&arena, &escaped, &comrak_options())
+        std::borrow::Cow::Owned(escaped)
     } else {
         // no escape
-        comrak::parse_document(&arena, cmark_text, &comrak_options())
+        std::borrow::Cow::Borrowed(cmark_text)
     };
+    let ast_root = comrak::parse_document(&arena, &cmark_text, &comrak_options());
+    restore_footnotes(&arena, ast_root, &cmark_text);
 
-    if let minidom::Node::Element(xml) = xml_from_ast(&ast_root) {
+    if let minidom::Node::Element(xml) = xml_from_ast(ast_root) {
         xml
     } else {
         // incase of no element, returns empty <body/>
@@ -84,28 +158,253 @@ pub fn xmldom_from_cmark(cmark_text: &str, escape_shortcode: bool) -> minidom::E
 /// Convert XML text back to CommonMark text
 ///
 /// If XML contains escaped shortcode, set escape_shortcode to true.
-pub fn cmark_from_xml(xml_str: &str, escape_shortcode: bool) -> minidom::Result<String> {
+pub fn cmark_from_xml(xml_str: &str, escape_shortcode: bool) -> Result<String> {
     let xml_root: minidom::Element = xml_str.parse()?;
-    Ok(cmark_from_xmldom(&xml_root, escape_shortcode))
+    cmark_from_xmldom(&xml_root, escape_shortcode)
 }
 
 /// Convert XML DOM back to CommonMark text
 ///
 /// If XML contains escaped shortcode, set escape_shortcode to true.
-pub fn cmark_from_xmldom(xml_root: &minidom::Element, escape_shortcode: bool) -> String {
+pub fn cmark_from_xmldom(xml_root: &minidom::Element, escape_shortcode: bool) -> Result<String> {
     // Convert XML to Comrak AST
     let arena = comrak::Arena::new();
-    let ast_root = ast_from_xml(&arena, &xml_root);
+    let ast_root = ast_from_xml(&arena, xml_root);
+    format_footnote_definitions(&arena, ast_root)?;
 
     // AST to plain CommonMark
     let mut buf = Vec::<u8>::new();
-    comrak::format_commonmark(ast_root, &comrak_options(), &mut buf).unwrap();
-    let cmark_text = String::from_utf8(buf).unwrap();
+    comrak::format_commonmark(ast_root, &comrak_options(), &mut buf)?;
+    let cmark_text = String::from_utf8_lossy(&buf);
     if escape_shortcode {
-        unescape_all_shortcodes(&cmark_text)
+        Ok(unescape_all_shortcodes(&cmark_text))
     } else {
-        cmark_text
+        Ok(cmark_text.into_owned())
+    }
+}
+
+/// Restore footnote labels and positions of definitions
+///
+/// Comrak renames footnotes to sequential numbers, moves definitions to the end of
+/// document, and drops unreferenced ones. Original label is taken from the line where
+/// the definition starts, and dropped definitions are parsed again from their lines.
+fn restore_footnotes<'a>(
+    arena: &'a comrak::Arena<comrak::nodes::AstNode<'a>>,
+    ast_root: &'a comrak::nodes::AstNode<'a>,
+    cmark_text: &str,
+) {
+    use comrak::nodes::NodeValue::*;
+
+    // Line numbers of Comrak do not count frontmatter
+    let mut lines = cmark_text.lines().collect::<Vec<_>>();
+    if let Some(first) = ast_root.first_child() {
+        if let FrontMatter(text) = &first.data.borrow().value {
+            lines.drain(..line_count(text).min(lines.len()));
+        }
+    }
+
+    // Definitions appended to the end, rename numbers to labels
+    let mut labels = std::collections::HashMap::new();
+    let mut definitions = vec![];
+    for node in ast_root.children() {
+        let ast = &mut node.data.borrow_mut();
+        // Line number is 1-based, and 0 if unknown
+        let line = (ast.start_line as usize)
+            .checked_sub(1)
+            .and_then(|ix| lines.get(ix))
+            .unwrap_or(&"");
+        if let FootnoteDefinition(name) = &mut ast.value {
+            let label = line
+                .split_once("[^")
+                .and_then(|(_, rest)| rest.split_once("]:"))
+                .map(|(label, _)| label.as_bytes().to_vec());
+            if let Some(label) = label {
+                labels.insert(name.clone(), label.clone());
+                *name = label;
+            }
+            definitions.push(node);
+        }
+    }
+    for node in ast_root.descendants() {
+        if let FootnoteReference(name) = &mut node.data.borrow_mut().value {
+            if let Some(label) = labels.get(name) {
+                *name = label.clone();
+            }
+        }
+    }
+
+    let unreferenced = unreferenced_footnotes(arena, ast_root, &lines, &definitions);
+    definitions.extend(unreferenced);
+
+    // Move definitions back to where they were, in the order of source
+    definitions.sort_by_key(|definition| definition.data.borrow().start_line);
+    for definition in &definitions {
+        definition.detach();
+    }
+    for definition in definitions {
+        let start_line = definition.data.borrow().start_line;
+        let next = ast_root.children().find(|node| {
+            !matches!(node.data.borrow().value, FootnoteDefinition(_))
+                && start_line < node.data.borrow().start_line
+        });
+        if let Some(next) = next {
+            next.insert_before(definition);
+        } else {
+            ast_root.append(definition);
+        }
+    }
+}
+
+/// Parse footnote definitions which Comrak dropped because nothing refers them
+///
+/// A line starting with `[^label]:` outside of code and HTML blocks, whose label is not
+/// defined in AST, starts a dropped definition. Its body is the rest of the line followed
+/// by indented, blank and lazy continuation lines, parsed as a separate document.
+fn unreferenced_footnotes<'a>(
+    arena: &'a comrak::Arena<comrak::nodes::AstNode<'a>>,
+    ast_root: &'a comrak::nodes::AstNode<'a>,
+    lines: &[&str],
+    defined: &[&'a comrak::nodes::AstNode<'a>],
+) -> Vec<&'a comrak::nodes::AstNode<'a>> {
+    use comrak::nodes::NodeValue::*;
+
+    // Lines of literal blocks, and lines where blocks start
+    let mut literals = vec![];
+    let mut starts = vec![];
+    for node in ast_root.descendants() {
+        let ast = node.data.borrow();
+        let start = ast.start_line as usize;
+        match &ast.value {
+            // with opening and closing fences
+            CodeBlock(code) if code.fenced => {
+                literals.push(start..=start + line_count(&code.literal) + 1)
+            }
+            HtmlBlock(html) => {
+                literals.push(start..=start + line_count(&html.literal).saturating_sub(1))
+            }
+            _ => {}
+        }
+        starts.push(start);
+    }
+    let labels = defined
+        .iter()
+        .filter_map(|node| match &node.data.borrow().value {
+            FootnoteDefinition(name) => Some(String::from_utf8_lossy(name).to_lowercase()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut options = comrak_options();
+    options.extension.front_matter_delimiter = None;
+    let mut definitions = vec![];
+    let mut ix = 0;
+    while let Some(line) = lines.get(ix) {
+        // Line number is 1-based
+        ix += 1;
+        let line_number = ix;
+        let Some((label, first)) = footnote_definition(line) else {
+            continue;
+        };
+        if labels.contains(&label.to_lowercase())
+            || literals.iter().any(|range| range.contains(&line_number))
+        {
+            continue;
+        }
+
+        let mut body = vec![first.trim_start()];
+        while let Some(line) = lines.get(ix) {
+            if let Some(indented) = line.strip_prefix("    ") {
+                body.push(indented);
+            } else if line.trim().is_empty() {
+                body.push("");
+            } else if body.last().is_some_and(|last| !last.trim().is_empty())
+                && !starts.contains(&(ix + 1))
+                && footnote_definition(line).is_none()
+            {
+                // Lazy continuation of paragraph
+                body.push(line);
+            } else {
+                break;
+            }
+            ix += 1;
+        }
+
+        let fragment = comrak::parse_document(arena, &body.join("\n"), &options);
+        let definition: &comrak::nodes::AstNode = arena.alloc(comrak::nodes::AstNode::from(
+            FootnoteDefinition(label.as_bytes().to_vec()),
+        ));
+        definition.data.borrow_mut().start_line = line_number as u32;
+        while let Some(child) = fragment.first_child() {
+            child.detach();
+            definition.append(child);
+        }
+        definitions.push(definition);
     }
+    definitions
+}
+
+/// Label and rest of the line, if the line starts footnote definition `[^label]:`
+fn footnote_definition(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let (label, rest) = trimmed.strip_prefix("[^")?.split_once("]:")?;
+    if label.is_empty() || label.contains(|c: char| c.is_whitespace() || c == '[' || c == ']') {
+        return None;
+    }
+    Some((label, rest))
+}
+
+/// Number of lines in literal of block
+fn line_count(literal: &[u8]) -> usize {
+    literal.iter().filter(|&&c| c == b'\n').count()
+}
+
+/// Replace footnote definitions with raw CommonMark blocks keeping their labels
+///
+/// Comrak writes definitions as `[^1]:` numbered in order regardless of their labels,
+/// so body of each definition is formatted separately and prefixed with `[^label]: `.
+fn format_footnote_definitions<'a>(
+    arena: &'a comrak::Arena<comrak::nodes::AstNode<'a>>,
+    ast_root: &'a comrak::nodes::AstNode<'a>,
+) -> Result<()> {
+    use comrak::nodes::NodeValue::*;
+    let definitions = ast_root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, FootnoteDefinition(_)))
+        .collect::<Vec<_>>();
+    for definition in definitions {
+        let body = arena.alloc(comrak::nodes::AstNode::from(Document));
+        while let Some(child) = definition.first_child() {
+            child.detach();
+            body.append(child);
+        }
+        let mut buf = Vec::<u8>::new();
+        comrak::format_commonmark(body, &comrak_options(), &mut buf)?;
+
+        // Continuation lines of body are indented
+        let ast = &mut definition.data.borrow_mut();
+        let FootnoteDefinition(name) = &ast.value else {
+            continue;
+        };
+        let mut literal = format!("[^{}]:", String::from_utf8_lossy(name));
+        for (ix, line) in String::from_utf8_lossy(&buf).trim_end().lines().enumerate() {
+            if ix == 0 {
+                literal += &format!(" {}", line);
+            } else if line.is_empty() {
+                literal += "\n";
+            } else {
+                literal += &format!("\n    {}", line);
+            }
+        }
+        literal += "\n";
+        ast.value = HtmlBlock(comrak::nodes::NodeHtmlBlock {
+            block_type: 0,
+            literal: literal.into_bytes(),
+        });
+    }
+    Ok(())
 }
 
 /// Escape shortcode {{...}} with <!--{{...}}-->, {%...%} with <!--{%...%}-->
@@ -194,17 +493,15 @@ fn xml_from_ast<'a>(ast_node: &'a comrak::nodes::AstNode<'a>) -> minidom::node::
     use comrak::nodes::{ListType::*, NodeValue::*};
     use minidom::node::Node;
     use minidom::Element;
-    use std::str::from_utf8;
+    let from_utf8 = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
     let ast = &ast_node.data.borrow();
 
     // Convert Markdown AST to XML nodes
     let xml_node = match &ast.value {
         Document => Node::Element(Element::bare("body", NS)),
-        FrontMatter(t) => Node::Element(
-            Element::builder("header", NS)
-                .append(std::str::from_utf8(t).unwrap())
-                .build(),
-        ),
+        FrontMatter(t) => {
+            Node::Element(Element::builder("header", NS).append(from_utf8(t)).build())
+        }
         BlockQuote => Node::Element(Element::bare("blockquote", NS)),
         List(nl) => {
             use comrak::nodes::{ListDelimType::*, ListType::*};
@@ -242,14 +539,14 @@ fn xml_from_ast<'a>(ast_node: &'a comrak::nodes::AstNode<'a>) -> minidom::node::
         DescriptionDetails => Node::Element(Element::bare("dd", NS)),
         CodeBlock(cb) => Node::Element(
             Element::builder("pre", NS)
-                .attr("info", from_utf8(&cb.info).unwrap())
-                .append(from_utf8(&cb.literal).unwrap())
+                .attr("info", from_utf8(&cb.info))
+                .append(from_utf8(&cb.literal))
                 .build(),
         ),
         HtmlBlock(hb) => Node::Element(
             Element::builder("object", NS)
                 .attr("type", hb.block_type as i32)
-                .attr("literal", from_utf8(&hb.literal).unwrap())
+                .attr("literal", from_utf8(&hb.literal))
                 .build(),
         ),
         Paragraph => Node::Element(Element::bare("p", NS)),
@@ -261,7 +558,7 @@ fn xml_from_ast<'a>(ast_node: &'a comrak::nodes::AstNode<'a>) -> minidom::node::
         ThematicBreak => Node::Element(Element::bare("hr", NS)),
         FootnoteDefinition(t) => Node::Element(
             Element::builder("footer", NS)
-                .attr("name", from_utf8(t).unwrap())
+                .attr("name", from_utf8(t))
                 .build(),
         ),
         Table(align) => {
@@ -292,7 +589,7 @@ fn xml_from_ast<'a>(ast_node: &'a comrak::nodes::AstNode<'a>) -> minidom::node::
         TableCell => Node::Element(Element::bare("td", NS)),
         Text(t) => {
             // Remove escape prefix (Workaround for shortcode escape)
-            Node::Text(String::from_utf8(t.clone()).unwrap().replace("$$$$", ""))
+            Node::Text(from_utf8(t).replace("$$$$", ""))
         }
         TaskItem(checked) => Node::Element(
             Element::builder("input", NS)
@@ -303,12 +600,12 @@ fn xml_from_ast<'a>(ast_node: &'a comrak::nodes::AstNode<'a>) -> minidom::node::
         LineBreak => Node::Element(Element::bare("br", NS)),
         Code(t) => Node::Element(
             Element::builder("code", NS)
-                .attr("literal", from_utf8(&t.literal).unwrap())
+                .attr("literal", from_utf8(&t.literal))
                 .build(),
         ),
         HtmlInline(t) => Node::Element(
             Element::builder("embed", NS)
-                .attr("literal", from_utf8(t).unwrap())
+                .attr("literal", from_utf8(t))
                 .build(),
         ),
         Emph => Node::Element(Element::bare("em", NS)),
@@ -317,19 +614,19 @@ fn xml_from_ast<'a>(ast_node: &'a comrak::nodes::AstNode<'a>) -> minidom::node::
         Superscript => Node::Element(Element::bare("sup", NS)),
         Link(url) => Node::Element(
             Element::builder("a", NS)
-                .attr("href", from_utf8(&url.url).unwrap())
-                .attr("title", from_utf8(&url.title).unwrap())
+                .attr("href", from_utf8(&url.url))
+                .attr("title", from_utf8(&url.title))
                 .build(),
         ),
         Image(url) => Node::Element(
             Element::builder("img", NS)
-                .attr("src", from_utf8(&url.url).unwrap())
-                .attr("title", from_utf8(&url.title).unwrap())
+                .attr("src", from_utf8(&url.url))
+                .attr("title", from_utf8(&url.title))
                 .build(),
         ),
         FootnoteReference(t) => Node::Element(
             Element::builder("sub", NS)
-                .attr("name", from_utf8(t).unwrap())
+                .attr("name", from_utf8(t))
                 .build(),
         ),
     };
@@ -342,7 +639,7 @@ fn xml_from_ast<'a>(ast_node: &'a comrak::nodes::AstNode<'a>) -> minidom::node::
                 if let Some(alt_node) = ast_node.first_child() {
                     if let Text(alt) = &alt_node.data.borrow().value {
                         // with alt
-                        xml_elm.set_attr("alt", from_utf8(alt).unwrap())
+                        xml_elm.set_attr("alt", from_utf8(alt))
                     }
                 };
             }
@@ -371,8 +668,8 @@ fn ast_from_xml<'a>(
         "body" => Document,
         "header" => FrontMatter(xml_elm.text().into_bytes()),
         "blockquote" => BlockQuote,
-        "ul" | "ol" => List(node_list_from_xml(&xml_elm)),
-        "li" => Item(node_list_from_xml(&xml_elm)),
+        "ul" | "ol" => List(node_list_from_xml(xml_elm)),
+        "li" => Item(node_list_from_xml(xml_elm)),
         "dl" => DescriptionList,
         "di" => DescriptionItem(comrak::nodes::NodeDescriptionItem {
             marker_offset: xml_elm.attr("offset").map_or(0, |v| v.parse().unwrap_or(0)),
@@ -403,17 +700,25 @@ fn ast_from_xml<'a>(
         "footer" => FootnoteDefinition(Vec::from(xml_elm.attr("name").unwrap_or(""))),
         "table" => {
             use comrak::nodes::TableAlignment::*;
-            let align = xml_elm
-                .attr("align")
-                .unwrap()
-                .chars()
-                .map(|c| match c {
-                    'l' => Left,
-                    'c' => Center,
-                    'r' => Right,
-                    _ => None,
-                })
-                .collect::<Vec<_>>();
+            let align = match xml_elm.attr("align") {
+                Some(align) => align
+                    .chars()
+                    .map(|c| match c {
+                        'l' => Left,
+                        'c' => Center,
+                        'r' => Right,
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+                // No alignment, as many columns as cells in header row
+                Option::None => {
+                    let columns = xml_elm
+                        .children()
+                        .next()
+                        .map_or(0, |row| row.children().count());
+                    vec![None; columns]
+                }
+            };
             Table(align)
         }
         "th" => TableRow(true),
@@ -512,7 +817,7 @@ fn comrak_options() -> comrak::ComrakOptions {
             tasklist: false,
             superscript: false,
             header_ids: None,
-            footnotes: false,
+            footnotes: true,
             description_lists: false,
             front_matter_delimiter: Some(String::from("+++")),
         },
@@ -530,3 +835,117 @@ fn comrak_options() -> comrak::ComrakOptions {
         },
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_frontmatter_lines() {
+        let read = |text: &str| read_cmark_with_frontmatter_format(&mut text.as_bytes()).unwrap();
+
+        // Delimiter in value is not closing one
+        assert_eq!(
+            read("---\ntitle: Hello---World\n---\n# Body\n"),
+            (
+                String::from("# Body\n"),
+                Some((
+                    FrontmatterFormat::Yaml,
+                    String::from("title: Hello---World\n")
+                ))
+            )
+        );
+        assert_eq!(
+            read("---\r\ntitle: Hello\r\n...\r\nBody\r\n"),
+            (
+                String::from("Body\r\n"),
+                Some((FrontmatterFormat::Yaml, String::from("title: Hello\r\n")))
+            )
+        );
+        assert_eq!(
+            read("+++\ntitle = \"a +++ b\"\n+++").1,
+            Some((
+                FrontmatterFormat::Toml,
+                String::from("title = \"a +++ b\"\n")
+            ))
+        );
+        assert!(read_cmark_with_frontmatter_format(&mut "---\ntitle: x ---\n".as_bytes()).is_err());
+
+        // Blank line after JSON object is kept with it
+        assert_eq!(
+            read("{ \"title\": \"Hello\" }\n\nBody\n"),
+            (
+                String::from("Body\n"),
+                Some((
+                    FrontmatterFormat::Json,
+                    String::from("{ \"title\": \"Hello\" }\n\n")
+                ))
+            )
+        );
+
+        // Thematic break is not delimiter
+        assert_eq!(read("-----\nBody\n").1, None);
+    }
+
+    #[test]
+    fn table_without_align() {
+        let xml = format!(
+            "<body xmlns=\"{}\"><table><th><td>A</td><td>B</td></th><tr><td>1</td><td>2</td></tr></table></body>",
+            NS
+        );
+        assert_eq!(
+            cmark_from_xml(&xml, false).unwrap(),
+            "| A | B |\n| --- | --- |\n| 1 | 2 |\n"
+        );
+    }
+
+    #[test]
+    fn xml_roundtrip() {
+        let cmark = "# Title\n\nText with `code` and [link](https://example.com \"title\").\n";
+        let xml = xml_from_cmark(cmark, false).unwrap();
+        assert!(xml.starts_with("<body"));
+        assert_eq!(cmark_from_xml(&xml, false).unwrap(), cmark);
+    }
+
+    #[test]
+    fn footnote_definitions() {
+        let roundtrip =
+            |cmark: &str| cmark_from_xmldom(&xmldom_from_cmark(cmark, false), false).unwrap();
+
+        // Unreferenced definition is kept
+        let cmark = "Text.\n\n[^unused]: Kept.\n\nNext.\n";
+        assert_eq!(roundtrip(cmark), cmark);
+
+        // Definitions at the end stay in the order of source, not of references
+        let cmark = "A[^b] and B[^a].\n\n[^a]: First.\n\n[^b]: Second.\n";
+        assert_eq!(roundtrip(cmark), cmark);
+
+        // Code block looking like definition is not rewritten
+        let cmark = "Text[^1].\n\n``` md\n[^1]:\n    foo\n```\n\n[^1]: Note\n    with two paragraphs.\n\n    Second.\n";
+        assert_eq!(
+            roundtrip(cmark),
+            "Text[^1].\n\n``` md\n[^1]:\n    foo\n```\n\n[^1]: Note\n    with two paragraphs.\n\n    Second.\n"
+        );
+
+        // Frontmatter at the beginning is kept
+        let cmark =
+            "+++\ntitle = \"Title\"\n+++\nText[^b].\n\n[^a]: Unused.\n\n[^b]: Used.\n\nEnd.\n";
+        let xml = xmldom_from_cmark(cmark, false);
+        assert_eq!(
+            xml.get_child("header", NS).map(|header| header.text()),
+            Some(String::from("+++\ntitle = \"Title\"\n+++\n"))
+        );
+        assert_eq!(cmark_from_xmldom(&xml, false).unwrap(), cmark);
+
+        // Definitions only in code are not added
+        let cmark = "Use `[^a]: b` for notes.\n\n``` md\n[^c]: d\n```\n";
+        assert_eq!(roundtrip(cmark), cmark);
+
+        // Body of unreferenced definition continues with indented and lazy lines
+        let cmark = "[^long]: First\nlazy.\n\n    Second.\n\n# Heading\n";
+        assert_eq!(
+            roundtrip(cmark),
+            "[^long]: First\n    lazy.\n\n    Second.\n\n# Heading\n"
+        );
+    }
+}
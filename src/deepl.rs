@@ -2,43 +2,107 @@
 //!
 //! DeepL REST API wrapper
 //!
+use crate::error::{Error, Result};
 
 pub struct Deepl {
     config: DeeplConfig,
+    /// HTTP client shared by all requests
+    client: reqwest::Client,
+    /// Limit number of requests in flight
+    semaphore: tokio::sync::Semaphore,
+    /// All requests wait until this, set when DeepL asks to slow down
+    backoff_until: std::sync::Mutex<Option<std::time::Instant>>,
 }
 
 impl Deepl {
     // New DeepL instance from default config file (deepl.toml or ~/.deepl.toml)
-    pub fn new() -> std::io::Result<Self> {
+    pub fn new() -> Result<Self> {
         let deepl_config = DeeplConfig::new()?;
+        let client = deepl_config.http.client()?;
 
-        Ok(Self {
-            config: deepl_config,
-        })
+        Self::with_config_and_client(deepl_config, client)
     }
 
     /// New DeepL instance from specific config file
-    pub fn with_config<P: AsRef<std::path::Path>>(config_path: P) -> std::io::Result<Self> {
+    pub fn with_config<P: AsRef<std::path::Path>>(config_path: P) -> Result<Self> {
         let deepl_config = DeeplConfig::with_config(config_path)?;
+        let client = deepl_config.http.client()?;
 
+        Self::with_config_and_client(deepl_config, client)
+    }
+
+    /// New DeepL instance from specific config file and HTTP client
+    ///
+    /// [http] section in config file is ignored.
+    pub fn with_client<P: AsRef<std::path::Path>>(
+        config_path: P,
+        client: reqwest::Client,
+    ) -> Result<Self> {
+        let deepl_config = DeeplConfig::with_config(config_path)?;
+
+        Self::with_config_and_client(deepl_config, client)
+    }
+
+    fn with_config_and_client(config: DeeplConfig, client: reqwest::Client) -> Result<Self> {
+        let semaphore = tokio::sync::Semaphore::new(config.concurrency.max(1));
         Ok(Self {
-            config: deepl_config,
+            config,
+            client,
+            semaphore,
+            backoff_until: std::sync::Mutex::new(None),
         })
     }
 
+    /// Set max number of concurrent requests, overriding config file
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.config.concurrency = concurrency;
+        self.semaphore = tokio::sync::Semaphore::new(concurrency.max(1));
+    }
+
+    /// Path of config file loaded
+    pub fn config_path(&self) -> &std::path::Path {
+        &self.config.path
+    }
+
+    /// Glossary ID used for the language pair
+    pub fn glossary_id(&self, from_lang: Language, to_lang: Language) -> Option<&str> {
+        self.config.glossary(from_lang, to_lang)
+    }
+
+    /// Test glossary ID is used for any language pair in config
+    pub fn glossary_in_use(&self, id: &str) -> bool {
+        self.config.glossary_in_use(id)
+    }
+
+    /// Set glossary ID used for the language pair, and save it to config file
+    pub fn update_glossary_id(
+        &mut self,
+        from_lang: Language,
+        to_lang: Language,
+        id: &str,
+    ) -> Result<()> {
+        self.update_glossary_ids(&[(from_lang, to_lang, id)])
+    }
+
+    /// Set glossary IDs used for the language pairs, and save them to config file at once
+    ///
+    /// Config is left unchanged if it can not be saved.
+    pub fn update_glossary_ids(&mut self, ids: &[(Language, Language, &str)]) -> Result<()> {
+        self.config.set_glossaries(ids)
+    }
+
     /// Translate single text string
-    #[allow(dead_code)]
     pub async fn translate(
         &self,
         from_lang: Language,
         to_lang: Language,
         formality: Formality,
         body: &str,
-    ) -> reqwest::Result<String> {
+    ) -> Result<String> {
         let mut result = self
             .translate_strings(from_lang, to_lang, formality, &vec![body])
             .await?;
-        if 0 < result.len() {
+        if !result.is_empty() {
             Ok(result.swap_remove(0))
         } else {
             // Empty response
@@ -52,7 +116,7 @@ impl Deepl {
         to_lang: Language,
         formality: Formality,
         body: &Vec<&str>,
-    ) -> reqwest::Result<Vec<String>> {
+    ) -> Result<Vec<String>> {
         let mut params = vec![
             ("source_lang", from_lang.as_src_langcode()),
             ("target_lang", to_lang.as_langcode()),
@@ -70,19 +134,17 @@ impl Deepl {
         }
 
         // Make DeepL API request
-        let client = reqwest::Client::new();
-        let resp = client
+        let request = self
+            .client
             .post(self.config.endpoint("translate"))
             .header(
                 "authorization",
                 format!("DeepL-Auth-Key {}", self.config.api_key),
             )
-            .form(&params)
-            .send()
-            .await?;
+            .form(&params);
 
-        // Returns error
-        resp.error_for_status_ref()?;
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
 
         // Parse response
         let deepl_resp = resp.json::<DeeplTranslationResponse>().await?;
@@ -100,7 +162,7 @@ impl Deepl {
         to_lang: Language,
         formality: Formality,
         xml_body: &str,
-    ) -> reqwest::Result<String> {
+    ) -> Result<String> {
         // Prepare request parameters
         let mut params = vec![
             ("source_lang", from_lang.as_src_langcode()),
@@ -122,23 +184,21 @@ impl Deepl {
         params.push(("text", xml_body));
 
         // Make DeepL API request
-        let client = reqwest::Client::new();
-        let resp = client
+        let request = self
+            .client
             .post(self.config.endpoint("translate"))
             .header(
                 "authorization",
                 format!("DeepL-Auth-Key {}", self.config.api_key),
             )
-            .form(&params)
-            .send()
-            .await?;
+            .form(&params);
 
-        // Returns error
-        resp.error_for_status_ref()?;
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
 
         // Parse response
         let mut deepl_resp = resp.json::<DeeplTranslationResponse>().await?;
-        if 0 < deepl_resp.translations.len() {
+        if !deepl_resp.translations.is_empty() {
             Ok(deepl_resp.translations.swap_remove(0).text)
         } else {
             // Empty response
@@ -153,45 +213,12 @@ impl Deepl {
         from_lang: Language,
         to_lang: Language,
         glossaries: &[(S, S)],
-    ) -> reqwest::Result<DeeplGlossary> {
-        // Remove spaces, empty items
-        let mut filtered_glossaries = glossaries
-            .iter()
-            .filter_map(|(from, to)| {
-                let from_trimed = from.as_ref().trim();
-                let to_trimed = to.as_ref().trim();
-                if from_trimed.is_empty() || to_trimed.is_empty() {
-                    None
-                } else {
-                    Some((from_trimed, to_trimed))
-                }
-            })
-            .collect::<Vec<_>>();
-
-        // Check duplicates
-        filtered_glossaries.sort_by(|(from1, _), (from2, _)| from1.cmp(from2));
-        filtered_glossaries.iter().fold("", |prev_from, (from, _)| {
-            if prev_from == *from {
-                // Duplicated
-                log::warn!("Duplicated key : \"{}\"", *from);
-            }
-            *from
-        });
-
-        // Make TSV text
-        let tsv: String = filtered_glossaries
-            .iter()
-            .map(|(from, to)| {
-                let row = format!("{}\t{}", from, to);
-                log::trace!("TSV: {}", row);
-                row
-            })
-            .collect::<Vec<String>>()
-            .join("\n");
+    ) -> Result<DeeplGlossary> {
+        let tsv = glossary_tsv(glossaries);
 
         // Make DeepL API request
-        let client = reqwest::Client::new();
-        let resp = client
+        let request = self
+            .client
             .post(self.config.endpoint("glossaries"))
             .header(
                 "authorization",
@@ -203,83 +230,350 @@ impl Deepl {
                 ("target_lang", to_lang.as_langcode()),
                 ("entries_format", "tsv"),
                 ("entries", &tsv),
-            ])
-            .send()
-            .await?;
+            ]);
 
-        if let Err(err) = resp.error_for_status_ref() {
-            // Returns error with printing details
-            if let Ok(err_body_text) = resp.text().await {
-                log::error!("{}", err_body_text);
-            }
-            Err(err)
-        } else {
-            // Success, parse response
-            let deepl_resp = resp.json::<DeeplGlossary>().await?;
-            Ok(deepl_resp)
-        }
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
+
+        // Parse response
+        let deepl_resp = resp.json::<DeeplGlossary>().await?;
+        Ok(deepl_resp)
     }
 
     /// List registered glossaries
-    pub async fn list_glossaries(&self) -> reqwest::Result<Vec<DeeplGlossary>> {
+    ///
+    /// Both v2 glossaries and v3 multilingual glossaries are listed.
+    pub async fn list_glossaries(&self) -> Result<Vec<DeeplGlossary>> {
         // Make DeepL API request
-        let client = reqwest::Client::new();
-        let resp = client
-            .get(self.config.endpoint("glossaries"))
+        let request = self
+            .client
+            .get(self.config.endpoint_v3("glossaries"))
             .header(
                 "authorization",
                 format!("DeepL-Auth-Key {}", self.config.api_key),
-            )
-            .send()
-            .await?;
+            );
 
-        // Returns error
-        resp.error_for_status_ref()?;
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
 
         // Parse response
         let deepl_resp = resp.json::<DeeplListGlossariesResponse>().await?;
-        Ok(deepl_resp.glossaries)
+        Ok(deepl_resp
+            .glossaries
+            .into_iter()
+            .map(DeeplGlossary::fill_from_dictionaries)
+            .collect())
+    }
+
+    /// Register new v3 multilingual glossary
+    ///
+    /// dictionaries are list of (from, to, entries) for each language pair.
+    pub async fn register_multilingual_glossary<S: AsRef<str>>(
+        &self,
+        name: &str,
+        dictionaries: &[DictionaryEntries<'_, S>],
+    ) -> Result<DeeplGlossary> {
+        let req = DeeplMultilingualGlossaryRequest {
+            name,
+            dictionaries: dictionaries
+                .iter()
+                .map(|(from_lang, to_lang, glossaries)| {
+                    DeeplDictionaryEntries::new(*from_lang, *to_lang, glossaries)
+                })
+                .collect(),
+        };
+
+        // Make DeepL API request
+        let request = self
+            .client
+            .post(self.config.endpoint_v3("glossaries"))
+            .header(
+                "authorization",
+                format!("DeepL-Auth-Key {}", self.config.api_key),
+            )
+            .json(&req);
+
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
+
+        // Parse response
+        let deepl_resp = resp.json::<DeeplGlossary>().await?;
+        Ok(deepl_resp.fill_from_dictionaries())
+    }
+
+    /// Replace entries of a language pair in v3 multilingual glossary
+    ///
+    /// The language pair is added if the glossary does not have it.
+    pub async fn replace_glossary_dictionary<S: AsRef<str>>(
+        &self,
+        id: &str,
+        from_lang: Language,
+        to_lang: Language,
+        glossaries: &[(S, S)],
+    ) -> Result<DeeplGlossaryDictionary> {
+        let req = DeeplDictionaryEntries::new(from_lang, to_lang, glossaries);
+
+        // Make DeepL API request
+        let request = self
+            .client
+            .put(
+                self.config
+                    .endpoint_v3(&format!("glossaries/{}/dictionaries", id)),
+            )
+            .header(
+                "authorization",
+                format!("DeepL-Auth-Key {}", self.config.api_key),
+            )
+            .json(&req);
+
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
+
+        // Parse response
+        let deepl_resp = resp.json::<DeeplGlossaryDictionary>().await?;
+        Ok(deepl_resp)
+    }
+
+    /// Get v3 multilingual glossary information
+    pub async fn get_multilingual_glossary(&self, id: &str) -> Result<DeeplGlossary> {
+        // Make DeepL API request
+        let request = self
+            .client
+            .get(self.config.endpoint_v3(&format!("glossaries/{}", id)))
+            .header(
+                "authorization",
+                format!("DeepL-Auth-Key {}", self.config.api_key),
+            );
+
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
+
+        // Parse response
+        let deepl_resp = resp.json::<DeeplGlossary>().await?;
+        Ok(deepl_resp.fill_from_dictionaries())
+    }
+
+    /// Get entries of a language pair in v3 multilingual glossary
+    pub async fn multilingual_glossary_entries(
+        &self,
+        id: &str,
+        from_lang: Language,
+        to_lang: Language,
+    ) -> Result<Vec<(String, String)>> {
+        // Make DeepL API request
+        let request = self
+            .client
+            .get(
+                self.config
+                    .endpoint_v3(&format!("glossaries/{}/entries", id)),
+            )
+            .header(
+                "authorization",
+                format!("DeepL-Auth-Key {}", self.config.api_key),
+            )
+            .query(&[
+                ("source_lang", from_lang.as_src_langcode()),
+                ("target_lang", to_lang.as_langcode()),
+            ]);
+
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
+
+        // Parse response
+        let deepl_resp = resp.json::<DeeplDictionaryEntriesResponse>().await?;
+        Ok(deepl_resp
+            .dictionaries
+            .iter()
+            .flat_map(|dict| parse_glossary_tsv(&dict.entries))
+            .collect())
+    }
+
+    /// Get registered glossary information
+    pub async fn get_glossary(&self, id: &str) -> Result<DeeplGlossary> {
+        // Make DeepL API request
+        let request = self
+            .client
+            .get(self.config.endpoint(&format!("glossaries/{}", id)))
+            .header(
+                "authorization",
+                format!("DeepL-Auth-Key {}", self.config.api_key),
+            );
+
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
+
+        // Parse response
+        let deepl_resp = resp.json::<DeeplGlossary>().await?;
+        Ok(deepl_resp)
+    }
+
+    /// Wait until registered glossary becomes ready
+    pub async fn wait_glossary_ready(
+        &self,
+        id: &str,
+        timeout: std::time::Duration,
+    ) -> Result<DeeplGlossary> {
+        let started = std::time::Instant::now();
+        loop {
+            let glossary = self.get_glossary(id).await?;
+            if glossary.ready || timeout <= started.elapsed() {
+                return Ok(glossary);
+            }
+            log::debug!("Glossary {} is not ready", id);
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+    }
+
+    /// Get entries of registered glossary, returns list of (from, to) terms
+    pub async fn glossary_entries(&self, id: &str) -> Result<Vec<(String, String)>> {
+        // Make DeepL API request
+        let request = self
+            .client
+            .get(self.config.endpoint(&format!("glossaries/{}/entries", id)))
+            .header(
+                "authorization",
+                format!("DeepL-Auth-Key {}", self.config.api_key),
+            )
+            .header("accept", "text/tab-separated-values");
+
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
+
+        // Parse TSV response
+        let tsv = resp.text().await?;
+        Ok(parse_glossary_tsv(&tsv))
     }
 
     /// Remove registered glossaries
-    pub async fn remove_glossary(&self, id: &str) -> reqwest::Result<()> {
+    pub async fn remove_glossary(&self, id: &str) -> Result<()> {
         // Make DeepL API request
-        let client = reqwest::Client::new();
-        let resp = client
+        let request = self
+            .client
             .delete(self.config.endpoint(&format!("glossaries/{}", id)))
             .header(
                 "authorization",
                 format!("DeepL-Auth-Key {}", self.config.api_key),
-            )
-            .send()
-            .await?;
+            );
 
-        // Check response
-        resp.error_for_status()?;
+        // Send request, retry on temporary errors
+        self.send(request).await?;
 
         Ok(())
     }
 
-    /// Get usage, returns translated characters
-    pub async fn get_usage(&self) -> reqwest::Result<i32> {
+    /// Remove v3 multilingual glossary
+    ///
+    /// v2 endpoint can not remove multilingual glossaries, while this one removes both.
+    pub async fn delete_multilingual_glossary(&self, id: &str) -> Result<()> {
         // Make DeepL API request
-        let client = reqwest::Client::new();
-        let resp = client
-            .get(self.config.endpoint("usage"))
+        let request = self
+            .client
+            .delete(self.config.endpoint_v3(&format!("glossaries/{}", id)))
             .header(
                 "authorization",
                 format!("DeepL-Auth-Key {}", self.config.api_key),
-            )
-            .send()
-            .await?;
+            );
 
-        // Returns error
-        resp.error_for_status_ref()?;
+        // Send request, retry on temporary errors
+        self.send(request).await?;
+
+        Ok(())
+    }
+
+    /// Get usage, returns translated characters
+    pub async fn get_usage(&self) -> Result<i32> {
+        // Make DeepL API request
+        let request = self.client.get(self.config.endpoint("usage")).header(
+            "authorization",
+            format!("DeepL-Auth-Key {}", self.config.api_key),
+        );
+
+        // Send request, retry on temporary errors
+        let resp = self.send(request).await?;
 
         // Parse response
         let deepl_resp = resp.json::<DeeplUsageResponse>().await?;
         Ok(deepl_resp.character_count)
     }
+
+    /// Send request, retry on 429, 5xx and network errors with exponential backoff
+    ///
+    /// Authentication and quota errors (403, 456) are returned immediately.
+    /// Number of concurrent requests is limited, and all requests wait while DeepL asks
+    /// to slow down.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let retry = &self.config.retry;
+        // Repeating GET, DELETE and translation has no side effect
+        let idempotent = request
+            .try_clone()
+            .and_then(|cloned| cloned.build().ok())
+            .is_some_and(|built| {
+                built.method() == reqwest::Method::GET
+                    || built.method() == reqwest::Method::DELETE
+                    || built.url().path().ends_with("/translate")
+            });
+        let mut attempt = 1;
+        loop {
+            let result = {
+                // Semaphore is never closed
+                let _permit = self.semaphore.acquire().await.ok();
+                // Requests queued for permit while rate limited wait as well
+                self.wait_backoff().await;
+
+                // Body of form and JSON requests can be cloned
+                let Some(cloned) = request.try_clone() else {
+                    return check_response(request.send().await?).await;
+                };
+                match cloned.send().await {
+                    Ok(resp) => check_response(resp).await,
+                    Err(err) => Err(Error::from(err)),
+                }
+            };
+
+            match result {
+                Err(err) if err.is_retryable(idempotent) && attempt < retry.max_attempts => {
+                    let delay = err.retry_after().unwrap_or_else(|| retry.backoff(attempt));
+                    log::warn!(
+                        "{}, retry {}/{} in {:.1} sec",
+                        err,
+                        attempt,
+                        retry.max_attempts - 1,
+                        delay.as_secs_f32()
+                    );
+                    if let Error::RateLimited { .. } = err {
+                        // Other requests also slow down
+                        self.set_backoff(delay);
+                    }
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Wait until backoff requested by rate limiting expires
+    async fn wait_backoff(&self) {
+        let backoff_until = *self
+            .backoff_until
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(until) = backoff_until {
+            tokio::time::sleep_until(tokio::time::Instant::from_std(until)).await;
+        }
+    }
+
+    /// Make all requests wait for delay
+    fn set_backoff(&self, delay: std::time::Duration) {
+        let mut backoff_until = self
+            .backoff_until
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let until = std::time::Instant::now() + delay;
+        if backoff_until.is_none_or(|current| current < until) {
+            *backoff_until = Some(until);
+        }
+    }
 }
 
 #[derive(Clone, Copy, serde::Deserialize)]
@@ -377,9 +671,9 @@ impl Language {
 }
 
 impl std::str::FromStr for Language {
-    type Err = std::io::Error;
+    type Err = Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> Result<Self> {
         let lowcase = s.to_ascii_lowercase();
         match lowcase.as_str() {
             "ar" => Ok(Self::Ar),
@@ -418,21 +712,22 @@ impl std::str::FromStr for Language {
             "zh" => Ok(Self::Zh),
             "zh-hans" => Ok(Self::ZhHans),
             "zh-hant" => Ok(Self::ZhHant),
-            _ => Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+            _ => Err(Error::UnsupportedLanguage(s.to_string())),
         }
     }
 }
 
 /// Translation output formality
-#[derive(Clone, Copy, serde::Deserialize)]
+#[derive(Clone, Copy, Default, serde::Deserialize)]
 pub enum Formality {
+    #[default]
     Default,
     Formal,
     Informal,
 }
 
 impl Formality {
-    pub fn to_str(&self) -> &'static str {
+    pub fn to_str(self) -> &'static str {
         match self {
             Self::Default => "default",
             Self::Formal => "prefer_more",
@@ -441,42 +736,50 @@ impl Formality {
     }
 }
 
-impl Default for Formality {
-    fn default() -> Self {
-        Self::Default
-    }
-}
-
 impl std::str::FromStr for Formality {
-    type Err = std::io::Error;
+    type Err = Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+    fn from_str(s: &str) -> Result<Self> {
         let lowcase = s.to_ascii_lowercase();
         match lowcase.as_str() {
             "default" => Ok(Self::Default),
             "formal" => Ok(Self::Formal),
             "informal" => Ok(Self::Informal),
-            _ => Err(std::io::Error::from(std::io::ErrorKind::InvalidInput)),
+            _ => Err(Error::UnsupportedFormality(s.to_string())),
         }
     }
 }
 
+/// Environment variable to override base_url in config file
+pub const BASE_URL_ENV: &str = "DEEPL_API_BASE_URL";
+
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "snake_case")]
 struct DeeplConfig {
     api_key: String,
+    /// API URL without version, e.g. "https://api.deepl.com"
+    #[serde(default)]
+    base_url: Option<String>,
+    /// Max number of concurrent requests
+    #[serde(default = "default_concurrency")]
+    concurrency: usize,
     glossaries: std::collections::HashMap<String, String>,
+    #[serde(default)]
+    retry: RetryConfig,
+    #[serde(default)]
+    http: HttpConfig,
+    /// Config file path, set after reading the file
+    #[serde(skip)]
+    path: std::path::PathBuf,
 }
 
 impl DeeplConfig {
     // Search default config file
-    fn new() -> std::io::Result<Self> {
+    fn new() -> Result<Self> {
         use std::path::PathBuf;
         let config_files = [
             PathBuf::new().join("deepl.toml"),
-            dirs::home_dir()
-                .unwrap_or(PathBuf::new())
-                .join(".deepl.toml"),
+            dirs::home_dir().unwrap_or_default().join(".deepl.toml"),
         ];
 
         for config_file in config_files {
@@ -486,7 +789,7 @@ impl DeeplConfig {
                     return Ok(conf);
                 }
                 Err(err) => {
-                    if err.kind() == std::io::ErrorKind::NotFound {
+                    if matches!(&err, Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound) {
                         log::debug!("Config file {:?} NOT found.", &config_file);
                     } else {
                         // Other err, stop searching
@@ -498,42 +801,299 @@ impl DeeplConfig {
         }
 
         // Config file not found
-        Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "deepl.toml NOT found",
-        ))
+        Err(Error::Config(String::from("deepl.toml NOT found")))
     }
 
     // Config from specific file
-    fn with_config<P: AsRef<std::path::Path>>(config_path: P) -> std::io::Result<Self> {
+    fn with_config<P: AsRef<std::path::Path>>(config_path: P) -> Result<Self> {
         use std::io::Read;
         let mut file = std::fs::File::open(&config_path)?;
 
         // Read .deepl as TOML
         let mut config = String::new();
         file.read_to_string(&mut config)?;
-        let deepl_config: DeeplConfig = toml::from_str(&config)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let mut deepl_config: DeeplConfig = toml::from_str(&config).map_err(|e| {
+            Error::Config(format!("Can not parse {:?} : {}", config_path.as_ref(), e))
+        })?;
+        deepl_config.path = config_path.as_ref().to_path_buf();
+
+        // Environment variable overrides base_url in config file
+        if let Some(base_url) = std::env::var(BASE_URL_ENV).ok().filter(|v| !v.is_empty()) {
+            log::debug!("{} = {}", BASE_URL_ENV, base_url);
+            deepl_config.base_url = Some(base_url);
+        }
 
         Ok(deepl_config)
     }
 
     // DeepL endpoint URL
     fn endpoint(&self, api: &str) -> String {
-        if self.api_key.ends_with(":fx") {
+        self.endpoint_version("v2", api)
+    }
+
+    // DeepL v3 endpoint URL
+    fn endpoint_v3(&self, api: &str) -> String {
+        self.endpoint_version("v3", api)
+    }
+
+    fn endpoint_version(&self, version: &str, api: &str) -> String {
+        format!("{}/{}/{}", self.base_url(), version, api)
+    }
+
+    // DeepL API URL, configured one or depending on API key
+    fn base_url(&self) -> &str {
+        if let Some(base_url) = &self.base_url {
+            base_url.trim_end_matches('/')
+        } else if self.api_key.ends_with(":fx") {
             // API free plan key
-            format!("https://api-free.deepl.com/v2/{}", api)
+            "https://api-free.deepl.com"
         } else {
             // API Pro key
-            format!("https://api.deepl.com/v2/{}", api)
+            "https://api.deepl.com"
         }
     }
 
     // Find glossary
-    fn glossary<'a>(&'a self, from_lang: Language, to_lang: Language) -> Option<&'a str> {
-        let glossary_key = format!("{}_{}", from_lang.as_src_langcode(), to_lang.as_langcode());
-        self.glossaries.get(&glossary_key).map(|v| v.as_str())
+    fn glossary(&self, from_lang: Language, to_lang: Language) -> Option<&str> {
+        self.glossaries
+            .get(&glossary_key(from_lang, to_lang))
+            .map(|v| v.as_str())
+    }
+
+    // Test glossary ID is used for any language pair
+    fn glossary_in_use(&self, id: &str) -> bool {
+        self.glossaries.values().any(|v| v == id)
+    }
+
+    // Update glossary IDs, rewrite config file preserving other contents
+    fn set_glossaries(&mut self, ids: &[(Language, Language, &str)]) -> Result<()> {
+        let config = std::fs::read_to_string(&self.path)?;
+        let mut doc = config
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|e| Error::Config(format!("Can not parse {:?} : {}", self.path, e)))?;
+        let glossaries = doc
+            .entry("glossaries")
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .ok_or_else(|| {
+                Error::Config(String::from("glossaries in config file is not a table"))
+            })?;
+        for (from_lang, to_lang, id) in ids {
+            let key = glossary_key(*from_lang, *to_lang);
+            if let Some(val) = glossaries
+                .get_mut(&key)
+                .and_then(|item| item.as_value_mut())
+            {
+                // Keep comments and spaces around the value
+                let decor = val.decor().clone();
+                *val = toml_edit::Value::from(*id);
+                *val.decor_mut() = decor;
+            } else {
+                glossaries.insert(&key, toml_edit::value(*id));
+            }
+        }
+        std::fs::write(&self.path, doc.to_string())?;
+
+        for (from_lang, to_lang, id) in ids {
+            let key = glossary_key(*from_lang, *to_lang);
+            log::debug!("Update {} = {} in {:?}", key, id, self.path);
+            self.glossaries.insert(key, id.to_string());
+        }
+        Ok(())
+    }
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+/// [http] section in config file
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default, rename_all = "snake_case")]
+struct HttpConfig {
+    /// Timeout to establish connection
+    connect_timeout_secs: u64,
+    /// Timeout of each read from connection
+    read_timeout_secs: u64,
+    /// Timeout of whole request, no limit if not set
+    timeout_secs: Option<u64>,
+    /// HTTP(S) proxy URL, e.g. "http://proxy.example.com:8080"
+    proxy: Option<String>,
+    /// PEM file of additional CA certificates
+    ca_cert: Option<std::path::PathBuf>,
+    /// User-Agent header
+    user_agent: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 30,
+            read_timeout_secs: 120,
+            timeout_secs: None,
+            proxy: None,
+            ca_cert: None,
+            user_agent: format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Build HTTP client
+    fn client(&self) -> Result<reqwest::Client> {
+        use std::time::Duration;
+
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .read_timeout(Duration::from_secs(self.read_timeout_secs))
+            .user_agent(&self.user_agent);
+        if let Some(timeout_secs) = self.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(timeout_secs));
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| Error::Config(format!("Invalid proxy \"{}\" : {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(ca_cert) = &self.ca_cert {
+            let pem = std::fs::read(ca_cert)?;
+            for cert in reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| Error::Config(format!("Can not read {:?} : {}", ca_cert, e)))?
+            {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// [retry] section in config file
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default, rename_all = "snake_case")]
+struct RetryConfig {
+    /// Number of attempts including the first request
+    max_attempts: u32,
+    /// Wait before the first retry, doubled for each retry
+    initial_delay_ms: u64,
+    /// Upper limit of wait
+    max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay_ms: 1000,
+            max_delay_ms: 60000,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Wait before n-th retry, exponential backoff with jitter
+    fn backoff(&self, attempt: u32) -> std::time::Duration {
+        use std::hash::{BuildHasher, Hasher};
+
+        let delay_ms = self
+            .initial_delay_ms
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(self.max_delay_ms);
+        // Random 50% - 100% of delay, avoid retrying at once with other clients
+        let random = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+        let jitter_ms = random % (delay_ms / 2 + 1);
+        std::time::Duration::from_millis(delay_ms - jitter_ms)
+    }
+}
+
+/// Check DeepL response status, returns error with response body
+async fn check_response(resp: reqwest::Response) -> Result<reqwest::Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+
+    let retry_after = resp
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_retry_after(v.trim()));
+    let message = resp.text().await.unwrap_or_default();
+    log::error!("DeepL returns {} : {}", status, message);
+    Err(Error::from_response(status, retry_after, message))
+}
+
+/// Parse Retry-After header, either delay in seconds or HTTP date
+fn parse_retry_after(value: &str) -> Option<std::time::Duration> {
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(secs));
     }
+    // Date already passed means no wait
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(std::time::SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// Make TSV text for glossary entries
+///
+/// Spaces around terms and empty entries are removed, duplicates are warned.
+fn glossary_tsv<S: AsRef<str>>(glossaries: &[(S, S)]) -> String {
+    // Remove spaces, empty items
+    let mut filtered_glossaries = glossaries
+        .iter()
+        .filter_map(|(from, to)| {
+            let from_trimed = from.as_ref().trim();
+            let to_trimed = to.as_ref().trim();
+            if from_trimed.is_empty() || to_trimed.is_empty() {
+                None
+            } else {
+                Some((from_trimed, to_trimed))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Check duplicates
+    filtered_glossaries.sort_by_key(|(from1, _)| *from1);
+    filtered_glossaries.iter().fold("", |prev_from, (from, _)| {
+        if prev_from == *from {
+            // Duplicated
+            log::warn!("Duplicated key : \"{}\"", *from);
+        }
+        *from
+    });
+
+    // Make TSV text
+    filtered_glossaries
+        .iter()
+        .map(|(from, to)| {
+            let row = format!("{}\t{}", from, to);
+            log::trace!("TSV: {}", row);
+            row
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Parse TSV glossary entries
+fn parse_glossary_tsv(tsv: &str) -> Vec<(String, String)> {
+    tsv.lines()
+        .filter_map(|row| {
+            row.split_once('\t')
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+        })
+        .collect()
+}
+
+/// Glossary entries of a language pair (from, to, entries)
+pub type DictionaryEntries<'a, S> = (Language, Language, &'a [(S, S)]);
+
+/// Key of glossaries table in config file, e.g. "en_ja"
+pub fn glossary_key(from_lang: Language, to_lang: Language) -> String {
+    format!("{}_{}", from_lang.as_src_langcode(), to_lang.as_langcode())
 }
 
 /// DeepL translation response JSON
@@ -560,16 +1120,102 @@ struct DeeplListGlossariesResponse {
 }
 
 /// DeepL response JSON for each glossaries
+///
+/// v2 glossary has single language pair in source_lang and target_lang.
+/// v3 multilingual glossary has language pairs in dictionaries.
 #[derive(serde::Deserialize, Debug)]
 #[serde(rename_all = "snake_case")]
 pub struct DeeplGlossary {
     pub glossary_id: String,
     pub name: String,
+    #[serde(default = "default_glossary_ready")]
     pub ready: bool,
+    #[serde(default)]
     pub source_lang: String,
+    #[serde(default)]
     pub target_lang: String,
     pub creation_time: String,
+    #[serde(default)]
     pub entry_count: i32,
+    #[serde(default)]
+    pub dictionaries: Vec<DeeplGlossaryDictionary>,
+}
+
+impl DeeplGlossary {
+    /// Language pairs (source_lang, target_lang) in this glossary
+    pub fn language_pairs(&self) -> Vec<(&str, &str)> {
+        if self.dictionaries.is_empty() {
+            vec![(self.source_lang.as_str(), self.target_lang.as_str())]
+        } else {
+            self.dictionaries
+                .iter()
+                .map(|d| (d.source_lang.as_str(), d.target_lang.as_str()))
+                .collect()
+        }
+    }
+
+    /// Fill v2 fields from v3 dictionaries
+    fn fill_from_dictionaries(mut self) -> Self {
+        if !self.dictionaries.is_empty() {
+            self.entry_count = self.dictionaries.iter().map(|d| d.entry_count).sum();
+            if let [dict] = self.dictionaries.as_slice() {
+                self.source_lang = dict.source_lang.clone();
+                self.target_lang = dict.target_lang.clone();
+            }
+        }
+        self
+    }
+}
+
+// v3 glossaries have no ready flag
+fn default_glossary_ready() -> bool {
+    true
+}
+
+/// DeepL v3 glossary response JSON for each language pairs
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub struct DeeplGlossaryDictionary {
+    pub source_lang: String,
+    pub target_lang: String,
+    #[serde(default)]
+    pub entry_count: i32,
+}
+
+/// DeepL v3 glossary request JSON
+#[derive(serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+struct DeeplMultilingualGlossaryRequest<'a> {
+    name: &'a str,
+    dictionaries: Vec<DeeplDictionaryEntries>,
+}
+
+/// DeepL v3 glossary entries of a language pair, request and response JSON
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+struct DeeplDictionaryEntries {
+    source_lang: String,
+    target_lang: String,
+    entries: String,
+    entries_format: String,
+}
+
+impl DeeplDictionaryEntries {
+    fn new<S: AsRef<str>>(from_lang: Language, to_lang: Language, glossaries: &[(S, S)]) -> Self {
+        Self {
+            source_lang: from_lang.as_src_langcode().to_string(),
+            target_lang: to_lang.as_langcode().to_string(),
+            entries: glossary_tsv(glossaries),
+            entries_format: String::from("tsv"),
+        }
+    }
+}
+
+/// DeepL v3 glossary entries response JSON
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+struct DeeplDictionaryEntriesResponse {
+    dictionaries: Vec<DeeplDictionaryEntries>,
 }
 
 /// DeepL usage response JSON
@@ -586,6 +1232,7 @@ mod test {
     use super::*;
 
     #[tokio::test]
+    #[ignore = "requires DeepL API key in deepl.toml and network"]
     async fn plain_text_translation() {
         let deepl = Deepl::new().unwrap();
 
@@ -600,4 +1247,121 @@ mod test {
             .unwrap();
         assert_eq!(&resp, "Hallo, Welt!");
     }
+
+    #[test]
+    fn update_glossary_in_config() {
+        let config_path = std::env::temp_dir().join("cmark_translate_update_glossary.toml");
+        std::fs::write(
+            &config_path,
+            "# DeepL config\napi_key = \"dummy:fx\"\n\n[glossaries]\nen_ja = \"old\" # comment\nja_en-us = \"keep\"\n",
+        )
+        .unwrap();
+
+        let mut deepl = Deepl::with_config(&config_path).unwrap();
+        deepl
+            .update_glossary_id(Language::En, Language::Ja, "new")
+            .unwrap();
+        deepl
+            .update_glossary_id(Language::En, Language::De, "added")
+            .unwrap();
+        assert_eq!(deepl.glossary_id(Language::En, Language::Ja), Some("new"));
+
+        let updated = std::fs::read_to_string(&config_path).unwrap();
+        assert_eq!(
+            updated,
+            "# DeepL config\napi_key = \"dummy:fx\"\n\n[glossaries]\nen_ja = \"new\" # comment\nja_en-us = \"keep\"\nen_de = \"added\"\n"
+        );
+        std::fs::remove_file(&config_path).unwrap();
+    }
+
+    #[test]
+    fn retry_backoff() {
+        let config: DeeplConfig = toml::from_str(
+            "api_key = \"dummy:fx\"\n[glossaries]\n[retry]\nmax_attempts = 3\ninitial_delay_ms = 100\nmax_delay_ms = 1000\n",
+        )
+        .unwrap();
+        assert_eq!(config.retry.max_attempts, 3);
+        for (attempt, max_ms) in [(1, 100), (2, 200), (4, 800), (5, 1000), (40, 1000)] {
+            let delay = config.retry.backoff(attempt);
+            assert!(std::time::Duration::from_millis(max_ms / 2) <= delay);
+            assert!(delay <= std::time::Duration::from_millis(max_ms));
+        }
+
+        let config: DeeplConfig = toml::from_str("api_key = \"dummy\"\n[glossaries]\n").unwrap();
+        assert_eq!(config.retry.max_attempts, 5);
+    }
+
+    #[test]
+    fn retry_after_header() {
+        use std::time::{Duration, SystemTime};
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let later = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let delay = parse_retry_after(&later).unwrap();
+        assert!(Duration::from_secs(55) < delay && delay <= Duration::from_secs(60));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn endpoint_url() {
+        let config: DeeplConfig = toml::from_str("api_key = \"dummy:fx\"\n[glossaries]\n").unwrap();
+        assert_eq!(
+            config.endpoint("translate"),
+            "https://api-free.deepl.com/v2/translate"
+        );
+
+        let config: DeeplConfig = toml::from_str(
+            "api_key = \"dummy:fx\"\nbase_url = \"http://127.0.0.1:8080/deepl/\"\n[glossaries]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.endpoint_v3("glossaries"),
+            "http://127.0.0.1:8080/deepl/v3/glossaries"
+        );
+    }
+
+    #[test]
+    fn http_client_config() {
+        let config: DeeplConfig = toml::from_str(
+            "api_key = \"dummy\"\n[glossaries]\n[http]\nconnect_timeout_secs = 5\ntimeout_secs = 600\nproxy = \"http://proxy.example.com:8080\"\nuser_agent = \"docs-ci\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.http.connect_timeout_secs, 5);
+        assert_eq!(config.http.read_timeout_secs, 120);
+        assert!(config.http.client().is_ok());
+
+        let config: DeeplConfig =
+            toml::from_str("api_key = \"dummy\"\n[glossaries]\n[http]\nproxy = \"::\"\n").unwrap();
+        assert!(matches!(config.http.client(), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn parse_multilingual_glossary() {
+        let json = r#"{
+            "glossary_id": "def3a26b-3e84-45b3-84ae-0c0aaf3525f7",
+            "name": "My Glossary",
+            "dictionaries": [
+                {"source_lang": "en", "target_lang": "ja", "entry_count": 2},
+                {"source_lang": "en", "target_lang": "de", "entry_count": 3}
+            ],
+            "creation_time": "2024-08-28T11:38:15.123Z"
+        }"#;
+        let glossary = serde_json::from_str::<DeeplGlossary>(json)
+            .unwrap()
+            .fill_from_dictionaries();
+        assert!(glossary.ready);
+        assert_eq!(glossary.entry_count, 5);
+        assert_eq!(glossary.language_pairs(), vec![("en", "ja"), ("en", "de")]);
+
+        let req = DeeplDictionaryEntries::new(
+            Language::En,
+            Language::Ja,
+            &[(" printer ", "プリンタ"), ("", "empty")],
+        );
+        assert_eq!(req.source_lang, "en");
+        assert_eq!(req.entries, "printer\tプリンタ");
+    }
 }
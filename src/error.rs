@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Error type of this crate
+//!
+
+/// Error type of cmark-translate
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Config file not found or invalid
+    Config(String),
+    /// File read / write error
+    Io(std::io::Error),
+    /// DeepL authentication failed, HTTP 403
+    Auth(String),
+    /// DeepL quota exceeded, HTTP 456
+    QuotaExceeded(String),
+    /// Too many requests to DeepL, HTTP 429
+    RateLimited {
+        /// Wait time requested by Retry-After header
+        retry_after: Option<std::time::Duration>,
+        message: String,
+    },
+    /// Other error response from DeepL
+    Api { status: u16, message: String },
+    /// Network error, or unexpected response
+    Http(reqwest::Error),
+    /// XML can not be converted back to CommonMark
+    Xml(String),
+    /// Language code not supported by DeepL
+    UnsupportedLanguage(String),
+    /// Invalid formality option
+    UnsupportedFormality(String),
+    /// Frontmatter can not be parsed
+    Frontmatter(String),
+    /// Glossary file can not be read or written
+    Glossary(String),
+    /// Translation memory file can not be read or written
+    Memory(String),
+    /// Existing translation does not correspond to previous source block by block
+    BlockMismatch(String),
+}
+
+/// Result type of cmark-translate
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Error response from DeepL, classified by HTTP status
+    pub(crate) fn from_response(
+        status: reqwest::StatusCode,
+        retry_after: Option<std::time::Duration>,
+        message: String,
+    ) -> Self {
+        match status.as_u16() {
+            403 => Self::Auth(message),
+            456 => Self::QuotaExceeded(message),
+            429 => Self::RateLimited {
+                retry_after,
+                message,
+            },
+            status => Self::Api { status, message },
+        }
+    }
+
+    /// Test the request may succeed by retrying later
+    ///
+    /// Rate limiting, server errors and network errors are retryable,
+    /// but authentication failure and quota exceeded are not.
+    /// Request interrupted while sending or receiving may have been processed by DeepL,
+    /// so it is retryable only if `idempotent`.
+    pub fn is_retryable(&self, idempotent: bool) -> bool {
+        match self {
+            Self::RateLimited { .. } => true,
+            Self::Api { status, .. } => 500 <= *status,
+            Self::Http(err) => {
+                err.is_timeout()
+                    || err.is_connect()
+                    || (idempotent && (err.is_request() || err.is_body()))
+            }
+            _ => false,
+        }
+    }
+
+    /// Wait time requested by DeepL
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            Self::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Config(msg) => write!(f, "Config error: {}", msg),
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+            Self::Auth(msg) => write!(f, "DeepL authentication failed: {}", msg),
+            Self::QuotaExceeded(msg) => write!(f, "DeepL quota exceeded: {}", msg),
+            Self::RateLimited {
+                retry_after: Some(retry_after),
+                message,
+            } => write!(
+                f,
+                "Too many requests, retry after {} sec: {}",
+                retry_after.as_secs(),
+                message
+            ),
+            Self::RateLimited { message, .. } => write!(f, "Too many requests: {}", message),
+            Self::Api { status, message } => write!(f, "DeepL error {}: {}", status, message),
+            Self::Http(err) => write!(f, "HTTP error: {}", err),
+            Self::Xml(msg) => write!(f, "XML error: {}", msg),
+            Self::UnsupportedLanguage(lang) => write!(f, "Unsupported language \"{}\"", lang),
+            Self::UnsupportedFormality(formality) => {
+                write!(f, "Unsupported formality \"{}\"", formality)
+            }
+            Self::Frontmatter(msg) => write!(f, "Frontmatter error: {}", msg),
+            Self::Glossary(msg) => write!(f, "Glossary error: {}", msg),
+            Self::Memory(msg) => write!(f, "Translation memory error: {}", msg),
+            Self::BlockMismatch(msg) => write!(f, "Can not align blocks: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Http(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Http(err)
+    }
+}
+
+impl From<minidom::Error> for Error {
+    fn from(err: minidom::Error) -> Self {
+        Self::Xml(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classify_error_response() {
+        let err = |status| Error::from_response(status, None, String::new());
+        assert!(matches!(
+            err(reqwest::StatusCode::FORBIDDEN),
+            Error::Auth(_)
+        ));
+        assert!(matches!(
+            err(reqwest::StatusCode::from_u16(456).unwrap()),
+            Error::QuotaExceeded(_)
+        ));
+        assert!(matches!(
+            err(reqwest::StatusCode::TOO_MANY_REQUESTS),
+            Error::RateLimited { .. }
+        ));
+        assert!(matches!(
+            err(reqwest::StatusCode::BAD_REQUEST),
+            Error::Api { status: 400, .. }
+        ));
+        assert!(err(reqwest::StatusCode::TOO_MANY_REQUESTS).is_retryable(false));
+        assert!(err(reqwest::StatusCode::SERVICE_UNAVAILABLE).is_retryable(false));
+        assert!(!err(reqwest::StatusCode::FORBIDDEN).is_retryable(false));
+        assert!(!err(reqwest::StatusCode::from_u16(456).unwrap()).is_retryable(false));
+
+        let mut md = "+++\ntitle = \"unterminated\"\n".as_bytes();
+        assert!(matches!(
+            crate::cmark_xml::read_cmark_with_frontmatter(&mut md),
+            Err(Error::Frontmatter(_))
+        ));
+        assert!(matches!(
+            "xx".parse::<crate::deepl::Language>(),
+            Err(Error::UnsupportedLanguage(_))
+        ));
+    }
+}
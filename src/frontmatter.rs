@@ -0,0 +1,873 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Find string values in YAML and JSON frontmatter by byte span
+//!
+//! Values are replaced in place, so that comments and formatting of frontmatter are kept.
+//!
+use crate::error::{Error, Result};
+
+/// Find string literals in JSON text with their dotted keys
+pub(crate) struct JsonScanner<'a> {
+    json: &'a str,
+    pos: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    pub(crate) fn new(json: &'a str) -> Self {
+        Self { json, pos: 0 }
+    }
+
+    /// Dotted key and byte span including quotes of each string value
+    pub(crate) fn strings(mut self) -> Result<Vec<(String, std::ops::Range<usize>)>> {
+        let mut strings = vec![];
+        self.value("", &mut strings)?;
+        Ok(strings)
+    }
+
+    fn value(
+        &mut self,
+        key: &str,
+        strings: &mut Vec<(String, std::ops::Range<usize>)>,
+    ) -> Result<()> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(b'}') {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                    let span = self.string()?;
+                    let child: String = serde_json::from_str(&self.json[span])
+                        .map_err(|e| Error::Frontmatter(e.to_string()))?;
+                    self.skip_whitespace();
+                    self.expect(b':')?;
+                    self.value(&child_key(key, &child), strings)?;
+                    self.skip_whitespace();
+                    if self.peek() == Some(b',') {
+                        self.pos += 1;
+                    }
+                }
+            }
+            Some(b'[') => {
+                // Items have the key of array
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(b']') {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                    self.value(key, strings)?;
+                    self.skip_whitespace();
+                    if self.peek() == Some(b',') {
+                        self.pos += 1;
+                    }
+                }
+            }
+            Some(b'"') => {
+                let span = self.string()?;
+                strings.push((key.to_string(), span));
+                Ok(())
+            }
+            Some(_) => {
+                // Number, true, false or null
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, b',' | b'}' | b']') && !c.is_ascii_whitespace())
+                {
+                    self.pos += 1;
+                }
+                Ok(())
+            }
+            None => Err(self.error()),
+        }
+    }
+
+    /// Span of string literal including quotes
+    fn string(&mut self) -> Result<std::ops::Range<usize>> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        loop {
+            match self.peek() {
+                Some(b'\\') => self.pos += 2,
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(start..self.pos);
+                }
+                Some(_) => self.pos += 1,
+                None => return Err(self.error()),
+            }
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.json.as_bytes().get(self.pos).copied()
+    }
+
+    fn error(&self) -> Error {
+        Error::Frontmatter(format!("Unexpected JSON at byte {}", self.pos))
+    }
+}
+
+/// Find string scalars in YAML text with their dotted keys
+///
+/// Block and flow collections, quoted and block scalars are supported, but complex keys are not.
+#[derive(Clone, Copy)]
+pub(crate) struct YamlScanner<'a> {
+    yaml: &'a str,
+    pos: usize,
+}
+
+/// Scalar found in YAML text
+pub(crate) struct YamlScalar {
+    /// Byte span including quotes, or content lines of block scalar
+    pub(crate) span: std::ops::Range<usize>,
+    style: YamlStyle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum YamlStyle {
+    Plain {
+        flow: bool,
+    },
+    SingleQuoted,
+    DoubleQuoted,
+    /// Literal "|" or folded ">" block scalar with indentation of its content
+    Block {
+        folded: bool,
+        indent: usize,
+    },
+}
+
+impl YamlScalar {
+    /// String value of scalar
+    pub(crate) fn value(&self, yaml: &str) -> String {
+        let text = &yaml[self.span.clone()];
+        match self.style {
+            YamlStyle::Plain { .. } => fold_lines(text),
+            YamlStyle::SingleQuoted => fold_lines(&text[1..text.len() - 1]).replace("''", "'"),
+            YamlStyle::DoubleQuoted => unescape_yaml(&fold_lines(&text[1..text.len() - 1])),
+            YamlStyle::Block { folded, indent } => {
+                let lines = text
+                    .split('\n')
+                    .map(|line| {
+                        line.get(indent..)
+                            .unwrap_or_default()
+                            .trim_end_matches('\r')
+                    })
+                    .collect::<Vec<_>>();
+                if folded {
+                    fold_lines(&lines.join("\n"))
+                } else {
+                    lines.join("\n")
+                }
+            }
+        }
+    }
+
+    /// YAML text of string replacing scalar, in the same style if possible
+    pub(crate) fn literal(&self, text: &str) -> String {
+        match self.style {
+            YamlStyle::Plain { flow } if is_plain_safe(text, flow) => text.to_string(),
+            YamlStyle::SingleQuoted if !text.contains('\n') => {
+                format!("'{}'", text.replace('\'', "''"))
+            }
+            YamlStyle::Block { folded, indent } => {
+                // Folded scalar needs empty line for each line break
+                let mut lines = vec![];
+                for (i, line) in text.split('\n').enumerate() {
+                    if folded && i > 0 {
+                        lines.push(String::new());
+                    }
+                    if !line.is_empty() || !folded {
+                        lines.push(
+                            format!("{}{}", " ".repeat(indent), line)
+                                .trim_end()
+                                .to_string(),
+                        );
+                    }
+                }
+                lines.join("\n")
+            }
+            // JSON string is also YAML double quoted scalar
+            _ => serde_json::to_string(text).unwrap_or_default(),
+        }
+    }
+}
+
+impl<'a> YamlScanner<'a> {
+    pub(crate) fn new(yaml: &'a str) -> Self {
+        Self { yaml, pos: 0 }
+    }
+
+    /// Dotted key and scalar of each string value
+    pub(crate) fn strings(mut self) -> Result<Vec<(String, YamlScalar)>> {
+        let mut strings = vec![];
+        if let Some(indent) = self.next_line() {
+            self.pos += indent;
+            if self.peek() == Some(b'{') {
+                self.flow_value("", &mut strings)?;
+                self.end_line()?;
+            } else if self.is_mapping_key() {
+                self.block_mapping("", indent, &mut strings)?;
+            } else {
+                return Err(Error::Frontmatter(String::from(
+                    "YAML root is not a mapping",
+                )));
+            }
+            if self.next_line().is_some() {
+                return Err(self.error());
+            }
+        }
+        Ok(strings)
+    }
+
+    /// Block mapping whose keys start at column indent
+    fn block_mapping(
+        &mut self,
+        key: &str,
+        indent: usize,
+        strings: &mut Vec<(String, YamlScalar)>,
+    ) -> Result<()> {
+        loop {
+            let child = self.key(false)?;
+            self.value(&child_key(key, &child), indent, true, strings)?;
+            match self.next_line() {
+                Some(n) if n == indent => self.pos += n,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Block sequence whose "-" indicators are at column indent
+    fn block_sequence(
+        &mut self,
+        key: &str,
+        indent: usize,
+        strings: &mut Vec<(String, YamlScalar)>,
+    ) -> Result<()> {
+        loop {
+            // Items have the key of sequence
+            self.expect(b'-')?;
+            self.value(key, indent, false, strings)?;
+            match self.next_line() {
+                Some(n) if n == indent && self.is_sequence_at(self.pos + n) => self.pos += n,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// Value after ":" or "-" of block collection at column indent, moving to next line
+    fn value(
+        &mut self,
+        key: &str,
+        indent: usize,
+        in_mapping: bool,
+        strings: &mut Vec<(String, YamlScalar)>,
+    ) -> Result<()> {
+        self.skip_spaces();
+        self.skip_properties();
+        let column = self.pos - self.yaml[..self.pos].rfind('\n').map_or(0, |i| i + 1);
+        match self.peek() {
+            None | Some(b'\n' | b'#') => {
+                // Value in following lines, or null
+                self.end_line()?;
+                match self.next_line() {
+                    Some(n)
+                        if n > indent
+                            || (in_mapping && n == indent && self.is_sequence_at(self.pos + n)) =>
+                    {
+                        self.pos += n;
+                        self.value(key, indent, in_mapping, strings)
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Some(b'-') if self.is_sequence_at(self.pos) => {
+                self.block_sequence(key, column, strings)
+            }
+            Some(b'|' | b'>') => self.block_scalar(key, indent, strings),
+            Some(b'[' | b'{') => {
+                self.flow_value(key, strings)?;
+                self.end_line()
+            }
+            Some(b'*') => {
+                // Alias shares translated anchor
+                self.plain_end(false);
+                self.end_line()
+            }
+            _ if self.is_mapping_key() => self.block_mapping(key, column, strings),
+            Some(b'"' | b'\'') => {
+                strings.push((key.to_string(), self.quoted()?));
+                self.end_line()
+            }
+            Some(_) => self.plain(key, indent, strings),
+        }
+    }
+
+    /// Plain scalar in block collection, which may continue in more indented lines
+    fn plain(
+        &mut self,
+        key: &str,
+        indent: usize,
+        strings: &mut Vec<(String, YamlScalar)>,
+    ) -> Result<()> {
+        let start = self.pos;
+        let mut end = self.plain_end(false);
+        self.end_line()?;
+        while let Some(next) = self.continuation(indent) {
+            self.pos = next;
+            end = self.plain_end(false);
+            self.end_line()?;
+        }
+        if !is_yaml_non_string(&self.yaml[start..end]) {
+            strings.push((
+                key.to_string(),
+                YamlScalar {
+                    span: start..end,
+                    style: YamlStyle::Plain { flow: false },
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /// Literal or folded block scalar, whose content lines are indented more than collection
+    fn block_scalar(
+        &mut self,
+        key: &str,
+        indent: usize,
+        strings: &mut Vec<(String, YamlScalar)>,
+    ) -> Result<()> {
+        let folded = self.peek() == Some(b'>');
+        self.pos += 1;
+        let mut content_indent = None;
+        while let Some(c @ (b'-' | b'+' | b'1'..=b'9')) = self.peek() {
+            if c.is_ascii_digit() {
+                content_indent = Some(indent + (c - b'0') as usize);
+            }
+            self.pos += 1;
+        }
+        self.end_line()?;
+
+        let mut span = None::<std::ops::Range<usize>>;
+        for line in self.yaml[self.pos..].split_inclusive('\n') {
+            let text = line.trim_end_matches(['\n', '\r']);
+            let line_indent = text.len() - text.trim_start_matches(' ').len();
+            if !text.trim().is_empty() {
+                let content_indent = *content_indent.get_or_insert(line_indent);
+                if line_indent < content_indent || content_indent <= indent {
+                    break;
+                }
+                let start = span.map_or(self.pos, |span| span.start);
+                span = Some(start..self.pos + text.len());
+            }
+            self.pos += line.len();
+        }
+        if let (Some(span), Some(indent)) = (span, content_indent) {
+            strings.push((
+                key.to_string(),
+                YamlScalar {
+                    span,
+                    style: YamlStyle::Block { folded, indent },
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /// Value in flow collection, which may span lines
+    fn flow_value(&mut self, key: &str, strings: &mut Vec<(String, YamlScalar)>) -> Result<()> {
+        self.skip_flow_whitespace();
+        self.skip_properties();
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                loop {
+                    self.skip_flow_whitespace();
+                    if self.peek() == Some(b'}') {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                    let child = self.key(true)?;
+                    self.flow_value(&child_key(key, &child), strings)?;
+                    self.skip_flow_whitespace();
+                    if self.peek() == Some(b',') {
+                        self.pos += 1;
+                    }
+                }
+            }
+            Some(b'[') => {
+                // Items have the key of sequence
+                self.pos += 1;
+                loop {
+                    self.skip_flow_whitespace();
+                    if self.peek() == Some(b']') {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                    self.flow_value(key, strings)?;
+                    self.skip_flow_whitespace();
+                    if self.peek() == Some(b',') {
+                        self.pos += 1;
+                    }
+                }
+            }
+            Some(b'"' | b'\'') => {
+                strings.push((key.to_string(), self.quoted()?));
+                Ok(())
+            }
+            Some(b'*') => {
+                self.plain_end(true);
+                Ok(())
+            }
+            Some(_) => {
+                let start = self.pos;
+                let end = self.plain_end(true);
+                if end == start {
+                    return Err(self.error());
+                }
+                if !is_yaml_non_string(&self.yaml[start..end]) {
+                    strings.push((
+                        key.to_string(),
+                        YamlScalar {
+                            span: start..end,
+                            style: YamlStyle::Plain { flow: true },
+                        },
+                    ));
+                }
+                Ok(())
+            }
+            None => Err(self.error()),
+        }
+    }
+
+    /// Mapping key, moving after ":"
+    fn key(&mut self, flow: bool) -> Result<String> {
+        let key = match self.peek() {
+            Some(b'"' | b'\'') => self.quoted()?.value(self.yaml),
+            Some(b'?') | None => return Err(self.error()),
+            Some(_) => {
+                let start = self.pos;
+                let end = self.plain_end(flow);
+                self.yaml[start..end].to_string()
+            }
+        };
+        self.skip_spaces();
+        self.expect(b':')?;
+        Ok(key)
+    }
+
+    /// Test mapping key starts at current position
+    fn is_mapping_key(&self) -> bool {
+        let mut probe = *self;
+        probe.key(false).is_ok()
+    }
+
+    /// Test "-" at pos is a block sequence indicator
+    fn is_sequence_at(&self, pos: usize) -> bool {
+        let bytes = self.yaml.as_bytes();
+        bytes.get(pos) == Some(&b'-')
+            && matches!(
+                bytes.get(pos + 1),
+                None | Some(b' ' | b'\t' | b'\r' | b'\n')
+            )
+    }
+
+    /// Span of single or double quoted scalar including quotes
+    fn quoted(&mut self) -> Result<YamlScalar> {
+        let start = self.pos;
+        let quote = self.peek();
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                Some(b'\\') if quote == Some(b'"') => self.pos += 2,
+                Some(b'\'')
+                    if quote == Some(b'\'') && self.yaml[self.pos + 1..].starts_with('\'') =>
+                {
+                    self.pos += 2
+                }
+                Some(c) if Some(c) == quote => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(_) => self.pos += 1,
+                None => return Err(self.error()),
+            }
+        }
+        let style = if quote == Some(b'"') {
+            YamlStyle::DoubleQuoted
+        } else {
+            YamlStyle::SingleQuoted
+        };
+        Ok(YamlScalar {
+            span: start..self.pos,
+            style,
+        })
+    }
+
+    /// Move to the end of plain scalar in the current line, and return the end without spaces
+    fn plain_end(&mut self, flow: bool) -> usize {
+        let start = self.pos;
+        while !self.at_plain_end(flow) {
+            self.pos += 1;
+        }
+        start + self.yaml[start..self.pos].trim_end().len()
+    }
+
+    /// Test plain scalar ends at current position
+    fn at_plain_end(&self, flow: bool) -> bool {
+        let next = self.yaml.as_bytes().get(self.pos + 1);
+        match self.peek() {
+            None | Some(b'\n') => true,
+            Some(b':') => {
+                matches!(next, None | Some(b' ' | b'\t' | b'\r' | b'\n'))
+                    || (flow && matches!(next, Some(b',' | b']' | b'}')))
+            }
+            Some(b'#') => self.yaml[..self.pos].ends_with([' ', '\t']),
+            Some(b',' | b'[' | b']' | b'{' | b'}') => flow,
+            _ => false,
+        }
+    }
+
+    /// Start of next line continuing plain scalar, which is indented more than collection
+    fn continuation(&self, indent: usize) -> Option<usize> {
+        let mut pos = self.pos;
+        for line in self.yaml[self.pos..].split_inclusive('\n') {
+            let content = line.trim_start_matches(' ');
+            if content.trim().is_empty() {
+                pos += line.len();
+                continue;
+            }
+            let line_indent = line.len() - content.len();
+            return (line_indent > indent && !content.starts_with('#'))
+                .then_some(pos + line_indent);
+        }
+        None
+    }
+
+    /// Skip empty and comment lines, and return indentation of the next line
+    ///
+    /// Position is left at the start of the next line.
+    fn next_line(&mut self) -> Option<usize> {
+        for line in self.yaml[self.pos..].split_inclusive('\n') {
+            let content = line.trim_start_matches(' ');
+            let trimmed = content.trim();
+            if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                return Some(line.len() - content.len());
+            }
+            self.pos += line.len();
+        }
+        None
+    }
+
+    /// Skip spaces and comment, and move to the next line
+    fn end_line(&mut self) -> Result<()> {
+        self.skip_spaces();
+        if self.peek() == Some(b'#') {
+            while !matches!(self.peek(), None | Some(b'\n')) {
+                self.pos += 1;
+            }
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some(b'\n') => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.error()),
+        }
+    }
+
+    /// Skip anchor and tag of node
+    fn skip_properties(&mut self) {
+        while matches!(self.peek(), Some(b'&' | b'!')) {
+            while !matches!(
+                self.peek(),
+                None | Some(b' ' | b'\t' | b'\r' | b'\n' | b',' | b']' | b'}')
+            ) {
+                self.pos += 1;
+            }
+            self.skip_spaces();
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip spaces, line breaks and comments in flow collection
+    fn skip_flow_whitespace(&mut self) {
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                Some(b'\n') => self.pos += 1,
+                Some(b'#') => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.yaml.as_bytes().get(self.pos).copied()
+    }
+
+    fn error(&self) -> Error {
+        Error::Frontmatter(format!("Unexpected YAML at byte {}", self.pos))
+    }
+}
+
+/// Fold line breaks of YAML scalar into spaces, and empty lines into line breaks
+fn fold_lines(text: &str) -> String {
+    let lines = text.split('\n').collect::<Vec<_>>();
+    let last = lines.len() - 1;
+    let mut folded = String::new();
+    let mut breaks = 0;
+    for (i, line) in lines.into_iter().enumerate() {
+        let mut line = line;
+        if i > 0 {
+            line = line.trim_start_matches([' ', '\t']);
+        }
+        if i < last {
+            line = line.trim_end_matches([' ', '\t', '\r']);
+        }
+        if 0 < i && i < last && line.is_empty() {
+            breaks += 1;
+            continue;
+        }
+        if i > 0 {
+            folded.push_str(&if breaks == 0 {
+                String::from(" ")
+            } else {
+                "\n".repeat(breaks)
+            });
+            breaks = 0;
+        }
+        folded.push_str(line);
+    }
+    folded
+}
+
+/// Unescape text of YAML double quoted scalar
+fn unescape_yaml(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => unescaped.push('\0'),
+            Some('a') => unescaped.push('\x07'),
+            Some('b') => unescaped.push('\x08'),
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('v') => unescaped.push('\x0b'),
+            Some('f') => unescaped.push('\x0c'),
+            Some('r') => unescaped.push('\r'),
+            Some('e') => unescaped.push('\x1b'),
+            Some('N') => unescaped.push('\u{85}'),
+            Some('_') => unescaped.push('\u{a0}'),
+            Some('L') => unescaped.push('\u{2028}'),
+            Some('P') => unescaped.push('\u{2029}'),
+            Some(c @ ('x' | 'u' | 'U')) => {
+                let len = match c {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+                let hex = chars.by_ref().take(len).collect::<String>();
+                if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    unescaped.push(c);
+                }
+            }
+            // '"', '\\', '/', ' ' and tab
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Test plain scalar may be resolved to other than string
+///
+/// Both YAML 1.1 and 1.2 are considered, as Hugo and Jekyll parse them differently.
+/// e.g. null, boolean including "yes" and "off", number including "1_000" and "1:30",
+/// and timestamp "2024-01-01".
+fn is_yaml_non_string(text: &str) -> bool {
+    let lower = text.to_ascii_lowercase();
+    let number = lower.trim_start_matches(['+', '-']);
+    matches!(
+        lower.as_str(),
+        "" | "~"
+            | "null"
+            | "true"
+            | "false"
+            | "yes"
+            | "no"
+            | "on"
+            | "off"
+            | "y"
+            | "n"
+            | "="
+            | "<<"
+    ) || matches!(number, ".inf" | ".nan")
+        // Digits with separators of number and timestamp, e.g. "0x1f", "1.5e+3", "2024-01-01t00:00:00z"
+        || (number.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+            && number
+                .chars()
+                .all(|c| c.is_ascii_hexdigit() || "xot:z._+- ".contains(c)))
+}
+
+/// Test text can be written as plain scalar, keeping it a string
+///
+/// Only text starting with a letter and containing letters, digits, spaces and
+/// common punctuation is allowed, others are quoted.
+fn is_plain_safe(text: &str, flow: bool) -> bool {
+    text.starts_with(char::is_alphabetic)
+        && !text.ends_with(char::is_whitespace)
+        && text.chars().all(|c| {
+            c.is_alphanumeric()
+                || c == ' '
+                || ".;!?'\"()/-_+".contains(c)
+                || (c == ',' && !flow)
+                || (!c.is_ascii() && !c.is_control() && !c.is_whitespace())
+        })
+        && !is_yaml_non_string(text)
+}
+
+/// Dotted key of child, e.g. "extra.summary"
+pub(crate) fn child_key(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scan_json_strings() {
+        let json = r#"{ "title": "A \"quoted\" title", "weight": 10,
+  "tags": ["x", "y"], "extra": { "seo": { "og_title": "OG" }, "draft": true } }"#;
+        let strings = JsonScanner::new(json)
+            .strings()
+            .unwrap()
+            .into_iter()
+            .map(|(key, span)| (key, &json[span]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            strings,
+            [
+                (String::from("title"), r#""A \"quoted\" title""#),
+                (String::from("tags"), r#""x""#),
+                (String::from("tags"), r#""y""#),
+                (String::from("extra.seo.og_title"), r#""OG""#),
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_yaml_strings() {
+        let yaml = "title: Plain # comment\nweight: 10\ntags: [a, 'b c']\nlinks:\n  - label: \"L\\u00e9\"\n    url: /x\nextra: &extra\n  note: |\n    one\n    two\n\n  draft: false\ncopy: *extra\n";
+        let strings = YamlScanner::new(yaml)
+            .strings()
+            .unwrap()
+            .into_iter()
+            .map(|(key, scalar)| (key, scalar.value(yaml)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            strings,
+            [
+                (String::from("title"), String::from("Plain")),
+                (String::from("tags"), String::from("a")),
+                (String::from("tags"), String::from("b c")),
+                (String::from("links.label"), String::from("L\u{e9}")),
+                (String::from("links.url"), String::from("/x")),
+                (String::from("extra.note"), String::from("one\ntwo")),
+            ]
+        );
+
+        // Style is kept unless text can't be written in it
+        let scalar = |style| YamlScalar { span: 0..0, style };
+        assert_eq!(
+            scalar(YamlStyle::Plain { flow: false }).literal("a, b"),
+            "a, b"
+        );
+        assert_eq!(
+            scalar(YamlStyle::Plain { flow: true }).literal("a, b"),
+            "\"a, b\""
+        );
+        assert_eq!(
+            scalar(YamlStyle::Plain { flow: false }).literal("true"),
+            "\"true\""
+        );
+        for text in [
+            "No",
+            "On",
+            "y",
+            "2024-01-01",
+            "1_000",
+            "1:30",
+            "5 min",
+            "a: b",
+            "a #b",
+        ] {
+            assert_eq!(
+                scalar(YamlStyle::Plain { flow: false }).literal(text),
+                serde_json::to_string(text).unwrap()
+            );
+        }
+        assert_eq!(
+            scalar(YamlStyle::Plain { flow: false }).literal("日本語のタイトル"),
+            "日本語のタイトル"
+        );
+        assert_eq!(scalar(YamlStyle::SingleQuoted).literal("it's"), "'it''s'");
+        assert_eq!(
+            scalar(YamlStyle::Block {
+                folded: true,
+                indent: 2
+            })
+            .literal("one\ntwo"),
+            "  one\n\n  two"
+        );
+        assert!(YamlScanner::new("- item\n").strings().is_err());
+    }
+}
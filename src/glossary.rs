@@ -1,12 +1,821 @@
 // SPDX-License-Identifier: MIT
 //!
-//! Read glossaries from .xlsx
+//! Read glossaries from .xlsx, .csv, .tsv and .tbx, write to .xlsx, .csv and .tsv
 //!
+use crate::error::{Error, Result};
 
+/// DeepL limit of a glossary term in UTF-8 bytes
+pub const MAX_TERM_BYTES: usize = 1024;
+/// DeepL limit of entire glossary in UTF-8 bytes
+pub const MAX_GLOSSARY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Glossary terms, one column per language
+#[derive(Debug, Default)]
+pub struct GlossaryTable {
+    /// Language codes in header row, lower case
+    pub languages: Vec<String>,
+    /// Terms in each row, same order as languages. Blank cell is empty string.
+    pub rows: Vec<Vec<String>>,
+}
+
+impl GlossaryTable {
+    /// Pick up (from, to) term pairs, skip rows with blank term
+    ///
+    /// Returns None if from or to language column is not found.
+    pub fn pairs(&self, from: &str, to: &str) -> Option<Vec<(String, String)>> {
+        let from_col = self.column(from)?;
+        let to_col = self.column(to)?;
+
+        let pairs = self
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let from_term = row[from_col].trim();
+                let to_term = row[to_col].trim();
+                if from_term.is_empty() || to_term.is_empty() {
+                    // Skip blank cells
+                    None
+                } else {
+                    Some((from_term.to_string(), to_term.to_string()))
+                }
+            })
+            .collect();
+        Some(pairs)
+    }
+
+    /// All combinations of (from, to) language codes in this table
+    ///
+    /// Variants of the same language, e.g. "en-us" and "en-gb", are not paired.
+    pub fn language_pairs(&self) -> Vec<(&str, &str)> {
+        let primary = |lang: &str| lang.split('-').next().unwrap_or_default().to_string();
+        let mut pairs = vec![];
+        for from in &self.languages {
+            for to in &self.languages {
+                if primary(from) != primary(to) {
+                    pairs.push((from.as_str(), to.as_str()));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Find column index of language
+    fn column(&self, lang: &str) -> Option<usize> {
+        // Prefer exact match, then regional variant
+        let lang = lang.trim().to_ascii_lowercase();
+        self.languages.iter().position(|l| *l == lang).or_else(|| {
+            self.languages
+                .iter()
+                .position(|l| langcode_matches(l, &lang))
+        })
+    }
+
+    /// Append rows of other table, adding new language columns if needed
+    fn merge(&mut self, other: GlossaryTable) {
+        let columns = other
+            .languages
+            .into_iter()
+            .map(|lang| {
+                if let Some(col) = self.languages.iter().position(|l| *l == lang) {
+                    col
+                } else {
+                    self.languages.push(lang);
+                    self.rows.iter_mut().for_each(|row| row.push(String::new()));
+                    self.languages.len() - 1
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for other_row in other.rows {
+            let mut row = vec![String::new(); self.languages.len()];
+            for (col, term) in columns.iter().zip(other_row) {
+                row[*col] = term;
+            }
+            self.rows.push(row);
+        }
+    }
+}
+
+/// Read glossary pairs from .xlsx, .csv, .tsv or .tbx
+///
+/// First row of each sheet should contain language codes, e.g. "en", "ja".
+/// Returns list of (from, to) terms.
 pub fn read_glossary<P: AsRef<std::path::Path>>(
-    _xlsx_path: P,
-    _from: &str,
-    _to: &str,
-) -> Result<Vec<(String, String)>, umya_spreadsheet::structs::XlsxError> {
-    todo!()
+    glossary_path: P,
+    from: &str,
+    to: &str,
+) -> Result<Vec<(String, String)>> {
+    let table = read_glossary_table(&glossary_path, &[] as &[&str])?;
+    table.pairs(from, to).ok_or_else(|| {
+        Error::Glossary(format!(
+            "Language column \"{}\" or \"{}\" NOT found in {:?}, found {:?}",
+            from,
+            to,
+            glossary_path.as_ref(),
+            table.languages
+        ))
+    })
+}
+
+/// Read all languages in glossary file as a table
+///
+/// File format is detected by extension, .xlsx, .csv, .tsv or .tbx.
+/// For .xlsx, rows of multiple sheets are merged. If sheets is empty, all sheets having
+/// language code header are read. sheets are ignored for other formats.
+pub fn read_glossary_table<P: AsRef<std::path::Path>, S: AsRef<str>>(
+    glossary_path: P,
+    sheets: &[S],
+) -> Result<GlossaryTable> {
+    let path = glossary_path.as_ref();
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if !sheets.is_empty() && ext != "xlsx" {
+        log::warn!("Sheet names are ignored for {:?}", path);
+    }
+
+    let table = match ext.as_str() {
+        "xlsx" => read_xlsx_table(path, sheets)?,
+        "csv" => read_delimited_table(path, b',')?,
+        "tsv" | "tab" | "txt" => read_delimited_table(path, b'\t')?,
+        "tbx" | "xml" => read_tbx_table(path)?,
+        _ => {
+            return Err(Error::Glossary(format!(
+                "Unsupported glossary file {:?}",
+                path
+            )))
+        }
+    };
+
+    if table.languages.is_empty() {
+        Err(Error::Glossary(format!(
+            "No language code found in {:?}",
+            path
+        )))
+    } else {
+        Ok(table)
+    }
+}
+
+/// Write glossary pairs to .xlsx, .csv or .tsv
+///
+/// File format is detected by extension. First row contains from and to language codes.
+pub fn write_glossary<P: AsRef<std::path::Path>, S: AsRef<str>>(
+    glossary_path: P,
+    from: &str,
+    to: &str,
+    glossaries: &[(S, S)],
+) -> Result<()> {
+    let path = glossary_path.as_ref();
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match ext.as_str() {
+        "xlsx" => {
+            let mut book = umya_spreadsheet::new_file();
+            let sheet = book
+                .get_sheet_mut(&0)
+                .ok_or_else(|| Error::Glossary(String::from("Can not create worksheet")))?;
+            sheet.get_cell_mut((1, 1)).set_value(from);
+            sheet.get_cell_mut((2, 1)).set_value(to);
+            for (row, (from_term, to_term)) in (2..).zip(glossaries) {
+                sheet.get_cell_mut((1, row)).set_value(from_term.as_ref());
+                sheet.get_cell_mut((2, row)).set_value(to_term.as_ref());
+            }
+            umya_spreadsheet::writer::xlsx::write(&book, path)
+                .map_err(|e| Error::Glossary(format!("Can not write {:?} : {}", path, e)))
+        }
+        "csv" | "tsv" | "tab" | "txt" => {
+            let delimiter = if ext == "csv" { b',' } else { b'\t' };
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(delimiter)
+                .quote_style(if delimiter == b'\t' {
+                    csv::QuoteStyle::Never
+                } else {
+                    csv::QuoteStyle::Necessary
+                })
+                .from_path(path)
+                .map_err(csv_error)?;
+            writer.write_record([from, to]).map_err(csv_error)?;
+            for (from_term, to_term) in glossaries {
+                writer
+                    .write_record([from_term.as_ref(), to_term.as_ref()])
+                    .map_err(csv_error)?;
+            }
+            Ok(writer.flush()?)
+        }
+        _ => Err(Error::Glossary(format!(
+            "Unsupported glossary file {:?}",
+            path
+        ))),
+    }
+}
+
+/// Problem found in glossary entries by check_glossary()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GlossaryIssue {
+    /// Language pair can not be registered
+    UnsupportedLanguagePair { from: String, to: String },
+    /// Same source term has different target terms
+    ConflictingTargets { term: String, targets: Vec<String> },
+    /// Source terms differ only in case
+    CaseOnlyDuplicate { terms: Vec<String> },
+    /// Term starts or ends with punctuation
+    Punctuation { term: String },
+    /// Term contains tab, newline or other control characters which break TSV
+    ControlCharacter { term: String },
+    /// Term is longer than MAX_TERM_BYTES
+    TermTooLong { term: String, bytes: usize },
+    /// Entire glossary is larger than MAX_GLOSSARY_BYTES
+    GlossaryTooLarge { bytes: usize },
+}
+
+impl GlossaryIssue {
+    /// true if DeepL will reject or misinterpret the glossary,
+    /// false if it is only suspicious.
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            Self::CaseOnlyDuplicate { .. } | Self::Punctuation { .. }
+        )
+    }
+}
+
+impl std::fmt::Display for GlossaryIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedLanguagePair { from, to } => {
+                write!(f, "unsupported language pair {} -> {}", from, to)
+            }
+            Self::ConflictingTargets { term, targets } => {
+                write!(f, "\"{}\" has conflicting targets {:?}", term, targets)
+            }
+            Self::CaseOnlyDuplicate { terms } => {
+                write!(f, "terms differ only in case {:?}", terms)
+            }
+            Self::Punctuation { term } => {
+                write!(f, "\"{}\" starts or ends with punctuation", term)
+            }
+            Self::ControlCharacter { term } => {
+                write!(f, "{:?} contains tab, newline or control character", term)
+            }
+            Self::TermTooLong { term, bytes } => {
+                write!(
+                    f,
+                    "\"{}\" is {} bytes, exceeds {} bytes",
+                    term, bytes, MAX_TERM_BYTES
+                )
+            }
+            Self::GlossaryTooLarge { bytes } => {
+                write!(
+                    f,
+                    "glossary is {} bytes, exceeds {} bytes",
+                    bytes, MAX_GLOSSARY_BYTES
+                )
+            }
+        }
+    }
+}
+
+/// Check glossary entries before registration
+///
+/// Entries are checked as is, without trimming spaces.
+pub fn check_glossary<S: AsRef<str>>(
+    from: &str,
+    to: &str,
+    glossaries: &[(S, S)],
+) -> Vec<GlossaryIssue> {
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+    let mut issues = vec![];
+
+    // Language pair
+    let from_lang = crate::deepl::Language::from_str(from);
+    let to_lang = crate::deepl::Language::from_str(to);
+    let supported = match (from_lang, to_lang) {
+        (Ok(from_lang), Ok(to_lang)) => from_lang.as_src_langcode() != to_lang.as_src_langcode(),
+        _ => false,
+    };
+    if !supported {
+        issues.push(GlossaryIssue::UnsupportedLanguagePair {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+
+    // Each terms
+    let mut total_bytes = 0;
+    for (from_term, to_term) in glossaries {
+        for term in [from_term.as_ref(), to_term.as_ref()] {
+            total_bytes += term.len() + 1;
+            if term.chars().any(|c| c.is_control()) {
+                issues.push(GlossaryIssue::ControlCharacter {
+                    term: term.to_string(),
+                });
+            }
+            if MAX_TERM_BYTES < term.len() {
+                issues.push(GlossaryIssue::TermTooLong {
+                    term: term.to_string(),
+                    bytes: term.len(),
+                });
+            }
+            let trimmed = term.trim();
+            let first = trimmed.chars().next();
+            let last = trimmed.chars().last();
+            if first.is_some_and(is_punctuation) || last.is_some_and(is_punctuation) {
+                issues.push(GlossaryIssue::Punctuation {
+                    term: term.to_string(),
+                });
+            }
+        }
+    }
+    if MAX_GLOSSARY_BYTES < total_bytes {
+        issues.push(GlossaryIssue::GlossaryTooLarge { bytes: total_bytes });
+    }
+
+    // Duplicated source terms
+    let mut targets: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (from_term, to_term) in glossaries {
+        let entry = targets.entry(from_term.as_ref().trim()).or_default();
+        if !entry.contains(&to_term.as_ref().trim()) {
+            entry.push(to_term.as_ref().trim());
+        }
+    }
+    let mut case_folded: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for (term, term_targets) in &targets {
+        if 1 < term_targets.len() {
+            issues.push(GlossaryIssue::ConflictingTargets {
+                term: term.to_string(),
+                targets: term_targets.iter().map(|t| t.to_string()).collect(),
+            });
+        }
+        case_folded
+            .entry(term.to_lowercase())
+            .or_default()
+            .push(term);
+    }
+    for terms in case_folded.into_values() {
+        if 1 < terms.len() {
+            issues.push(GlossaryIssue::CaseOnlyDuplicate {
+                terms: terms.into_iter().map(String::from).collect(),
+            });
+        }
+    }
+
+    issues
+}
+
+/// ASCII and common CJK punctuation
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || "、。，．・：；？！「」『』（）【】〈〉《》".contains(c)
+}
+
+/// Read .xlsx as a table
+fn read_xlsx_table<S: AsRef<str>>(
+    xlsx_path: &std::path::Path,
+    sheets: &[S],
+) -> Result<GlossaryTable> {
+    let book = umya_spreadsheet::reader::xlsx::read(xlsx_path)
+        .map_err(|e| Error::Glossary(format!("Can not read {:?} : {}", xlsx_path, e)))?;
+
+    let mut table = GlossaryTable::default();
+    if sheets.is_empty() {
+        // Read all sheets, skip ones without language header
+        for sheet in book.get_sheet_collection() {
+            let sheet_table = read_sheet(sheet);
+            if sheet_table.languages.is_empty() {
+                log::info!("Skip sheet \"{}\", no language code", sheet.get_name());
+            } else {
+                table.merge(sheet_table);
+            }
+        }
+    } else {
+        // Read selected sheets
+        for sheet_name in sheets {
+            let sheet = book.get_sheet_by_name(sheet_name.as_ref()).ok_or_else(|| {
+                Error::Glossary(format!(
+                    "Sheet \"{}\" NOT found in {:?}",
+                    sheet_name.as_ref(),
+                    xlsx_path
+                ))
+            })?;
+            let sheet_table = read_sheet(sheet);
+            if sheet_table.languages.is_empty() {
+                return Err(Error::Glossary(format!(
+                    "No language code in header of \"{}\"",
+                    sheet.get_name()
+                )));
+            }
+            table.merge(sheet_table);
+        }
+    }
+
+    Ok(table)
+}
+
+/// Read language columns of a worksheet
+fn read_sheet(sheet: &umya_spreadsheet::Worksheet) -> GlossaryTable {
+    // Find language columns in header row
+    let (max_col, max_row) = sheet.get_highest_column_and_row();
+    let (columns, languages): (Vec<u32>, Vec<String>) = (1..=max_col)
+        .filter_map(|col| language_header(&sheet.get_value((col, 1))).map(|lang| (col, lang)))
+        .unzip();
+    log::debug!(
+        "Glossary columns in \"{}\": {:?} = {:?}",
+        sheet.get_name(),
+        languages,
+        columns
+    );
+
+    let rows = (2..=max_row)
+        .filter(|row| {
+            // Skip rows merged over multiple cells, e.g. section titles
+            !is_merged_row(sheet, *row, &columns)
+        })
+        .map(|row| {
+            columns
+                .iter()
+                .map(|col| sheet.get_value((*col, row)).trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .filter(|row| row.iter().any(|term| !term.is_empty()))
+        .collect();
+
+    GlossaryTable { languages, rows }
+}
+
+/// Read CSV or TSV with language code header row as a table
+fn read_delimited_table(path: &std::path::Path, delimiter: u8) -> Result<GlossaryTable> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .quoting(delimiter != b'\t')
+        .flexible(true)
+        .from_path(path)
+        .map_err(csv_error)?;
+
+    // Find language columns in header row
+    let (columns, languages): (Vec<usize>, Vec<String>) = reader
+        .headers()
+        .map_err(csv_error)?
+        .iter()
+        .enumerate()
+        .filter_map(|(col, header)| language_header(header).map(|lang| (col, lang)))
+        .unzip();
+    log::debug!("Glossary columns: {:?} = {:?}", languages, columns);
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let row = columns
+            .iter()
+            .map(|col| record.get(*col).unwrap_or_default().trim().to_string())
+            .collect::<Vec<_>>();
+        if row.iter().any(|term| !term.is_empty()) {
+            rows.push(row);
+        }
+    }
+
+    Ok(GlossaryTable { languages, rows })
+}
+
+/// CSV error, keeping I/O error as it is
+fn csv_error(err: csv::Error) -> Error {
+    match err.into_kind() {
+        csv::ErrorKind::Io(err) => Error::Io(err),
+        kind => Error::Glossary(format!("{:?}", kind)),
+    }
+}
+
+/// Read TBX (TermBase eXchange) as a table
+///
+/// Supports both TBX v2 (martif, termEntry, langSet) and v3 (tbx, conceptEntry, langSec).
+/// The first term of each language in an entry is used.
+fn read_tbx_table(path: &std::path::Path) -> Result<GlossaryTable> {
+    let xml = std::fs::read_to_string(path)?;
+    let root = parse_tbx(&xml)
+        .map_err(|e| Error::Glossary(format!("Can not parse {:?} : {}", path, e)))?;
+
+    // Collect concept entries
+    let mut entries = vec![];
+    find_tbx_elements(&root, &["termEntry", "conceptEntry"], &mut entries);
+
+    let mut table = GlossaryTable::default();
+    for entry in entries {
+        let mut lang_sets = vec![];
+        find_tbx_elements(entry, &["langSet", "langSec"], &mut lang_sets);
+
+        let mut entry_table = GlossaryTable {
+            languages: vec![],
+            rows: vec![vec![]],
+        };
+        for lang_set in lang_sets {
+            let Some(lang) = lang_set.attr("xml:lang").and_then(language_header) else {
+                continue;
+            };
+            let mut terms = vec![];
+            find_tbx_elements(lang_set, &["term"], &mut terms);
+            if let Some(term) = terms.first() {
+                if !entry_table.languages.contains(&lang) {
+                    entry_table.languages.push(lang);
+                    entry_table.rows[0].push(term.text().trim().to_string());
+                }
+            }
+        }
+        if !entry_table.languages.is_empty() {
+            table.merge(entry_table);
+        }
+    }
+
+    Ok(table)
+}
+
+/// Parse TBX XML
+///
+/// TBX files usually have no XML namespace and DOCTYPE, which minidom can not handle.
+fn parse_tbx(xml: &str) -> minidom::Result<minidom::Element> {
+    // Remove <!DOCTYPE ...>
+    let xml = if let Some((prolog, rest)) = xml.split_once("<!DOCTYPE") {
+        let rest = rest.split_once('>').map_or("", |(_, rest)| rest);
+        format!("{}{}", prolog, rest)
+    } else {
+        xml.to_string()
+    };
+
+    match xml.parse::<minidom::Element>() {
+        Err(minidom::Error::MissingNamespace) => {
+            // Add dummy default namespace to root element
+            let root_start = xml
+                .match_indices('<')
+                .map(|(pos, _)| pos)
+                .find(|pos| {
+                    xml[pos + 1..]
+                        .chars()
+                        .next()
+                        .is_some_and(|c| c.is_alphabetic())
+                })
+                .ok_or(minidom::Error::MissingNamespace)?;
+            let name_end = xml[root_start..]
+                .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .map_or(xml.len(), |pos| root_start + pos);
+            let xml = format!("{} xmlns=\"urn:tbx\"{}", &xml[..name_end], &xml[name_end..]);
+            xml.parse()
+        }
+        result => result,
+    }
+}
+
+/// Find descendant elements by name, not descending into found elements
+fn find_tbx_elements<'a>(
+    elm: &'a minidom::Element,
+    names: &[&str],
+    found: &mut Vec<&'a minidom::Element>,
+) {
+    for child in elm.children() {
+        if names.contains(&child.name()) {
+            found.push(child);
+        } else {
+            find_tbx_elements(child, names, found);
+        }
+    }
+}
+
+/// Normalized language code if header is supported language code
+///
+/// Unsupported regional variant, e.g. "ja-JP", falls back to primary language "ja".
+fn language_header(header: &str) -> Option<String> {
+    use std::str::FromStr;
+    let header = header.trim().to_ascii_lowercase();
+    let primary = header.split('-').next().unwrap_or_default();
+    if crate::deepl::Language::from_str(&header).is_ok() {
+        Some(header)
+    } else if crate::deepl::Language::from_str(primary).is_ok() {
+        Some(primary.to_string())
+    } else {
+        None
+    }
+}
+
+/// Test header cell matches language code
+///
+/// Header without region ("en") matches any regional variant ("en-us") and vice versa.
+fn langcode_matches(header: &str, lang: &str) -> bool {
+    let header = header.trim().to_ascii_lowercase();
+    let lang = lang.trim().to_ascii_lowercase();
+    if header.is_empty() {
+        false
+    } else if header == lang {
+        true
+    } else {
+        let (header_primary, header_region) = header.split_once('-').unwrap_or((&header, ""));
+        let (lang_primary, lang_region) = lang.split_once('-').unwrap_or((&lang, ""));
+        header_primary == lang_primary && (header_region.is_empty() || lang_region.is_empty())
+    }
+}
+
+/// Test the row is covered by merged cells which contain any of columns
+fn is_merged_row(sheet: &umya_spreadsheet::Worksheet, row: u32, columns: &[u32]) -> bool {
+    sheet.get_merge_cells().iter().any(|range| {
+        let col_range = (
+            range.get_coordinate_start_col().map(|c| *c.get_num()),
+            range.get_coordinate_end_col().map(|c| *c.get_num()),
+        );
+        let row_range = (
+            range.get_coordinate_start_row().map(|r| *r.get_num()),
+            range.get_coordinate_end_row().map(|r| *r.get_num()),
+        );
+        match (col_range, row_range) {
+            ((Some(col_start), Some(col_end)), (Some(row_start), Some(row_end))) => {
+                let in_row = row_start <= row && row <= row_end;
+                in_row
+                    && columns
+                        .iter()
+                        .any(|col| col_start <= *col && *col <= col_end)
+            }
+            _ => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_xlsx_glossary() {
+        let xlsx_path = std::env::temp_dir().join("cmark_translate_read_xlsx_glossary.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_sheet_mut(&0).unwrap();
+        sheet.get_cell_mut("A1").set_value("ja");
+        sheet.get_cell_mut("B1").set_value("EN");
+        sheet.get_cell_mut("A2").set_value("翻訳");
+        sheet.get_cell_mut("B2").set_value(" translation ");
+        sheet.get_cell_mut("A3").set_value("Section title");
+        sheet.add_merge_cells("A3:B3");
+        sheet.get_cell_mut("A5").set_value("用語集");
+        sheet.get_cell_mut("B5").set_value("glossary");
+        umya_spreadsheet::writer::xlsx::write(&book, &xlsx_path).unwrap();
+
+        let glossaries = read_glossary(&xlsx_path, "en-us", "ja").unwrap();
+        assert_eq!(
+            glossaries,
+            vec![
+                ("translation".to_string(), "翻訳".to_string()),
+                ("glossary".to_string(), "用語集".to_string()),
+            ]
+        );
+        assert!(read_glossary(&xlsx_path, "en", "de").is_err());
+
+        std::fs::remove_file(&xlsx_path).unwrap();
+    }
+
+    #[test]
+    fn read_multi_sheet_glossary() {
+        let xlsx_path = std::env::temp_dir().join("cmark_translate_read_multi_sheet_glossary.xlsx");
+        let mut book = umya_spreadsheet::new_file();
+        let sheet = book.get_sheet_mut(&0).unwrap();
+        sheet.get_cell_mut("A1").set_value("en");
+        sheet.get_cell_mut("B1").set_value("ja");
+        sheet.get_cell_mut("A2").set_value("glossary");
+        sheet.get_cell_mut("B2").set_value("用語集");
+        let sheet = book.new_sheet("Product").unwrap();
+        sheet.get_cell_mut("A1").set_value("Note");
+        sheet.get_cell_mut("B1").set_value("de");
+        sheet.get_cell_mut("C1").set_value("en");
+        sheet.get_cell_mut("A2").set_value("comment");
+        sheet.get_cell_mut("B2").set_value("Drucker");
+        sheet.get_cell_mut("C2").set_value("printer");
+        book.new_sheet("Notes").unwrap();
+        umya_spreadsheet::writer::xlsx::write(&book, &xlsx_path).unwrap();
+
+        let table = read_glossary_table(&xlsx_path, &[] as &[&str]).unwrap();
+        assert_eq!(table.languages, vec!["en", "ja", "de"]);
+        assert_eq!(table.language_pairs().len(), 6);
+        assert_eq!(
+            table.pairs("en", "de").unwrap(),
+            vec![("printer".to_string(), "Drucker".to_string())]
+        );
+
+        let table = read_glossary_table(&xlsx_path, &["Sheet1"]).unwrap();
+        assert_eq!(table.languages, vec!["en", "ja"]);
+        assert!(read_glossary_table(&xlsx_path, &["Notes"]).is_err());
+
+        std::fs::remove_file(&xlsx_path).unwrap();
+    }
+
+    #[test]
+    fn read_csv_tsv_glossary() {
+        let csv_path = std::env::temp_dir().join("cmark_translate_read_glossary.csv");
+        std::fs::write(
+            &csv_path,
+            "en,Note,ja\n\"Hello, World\",greeting,こんにちは\n,,\nprinter,,プリンタ\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_glossary(&csv_path, "en", "ja").unwrap(),
+            vec![
+                ("Hello, World".to_string(), "こんにちは".to_string()),
+                ("printer".to_string(), "プリンタ".to_string()),
+            ]
+        );
+        std::fs::remove_file(&csv_path).unwrap();
+
+        let tsv_path = std::env::temp_dir().join("cmark_translate_read_glossary.tsv");
+        std::fs::write(&tsv_path, "ja\ten\n\"引用\"\t\"quote\"\n").unwrap();
+        assert_eq!(
+            read_glossary(&tsv_path, "en", "ja").unwrap(),
+            vec![("\"quote\"".to_string(), "\"引用\"".to_string())]
+        );
+        std::fs::remove_file(&tsv_path).unwrap();
+    }
+
+    #[test]
+    fn read_tbx_glossary() {
+        let tbx_path = std::env::temp_dir().join("cmark_translate_read_glossary.tbx");
+        std::fs::write(
+            &tbx_path,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE martif SYSTEM "TBXcoreStructV02.dtd">
+<martif type="TBX" xml:lang="en">
+  <text><body>
+    <termEntry id="c1">
+      <langSet xml:lang="en-US"><tig><term>printer</term></tig></langSet>
+      <langSet xml:lang="ja-JP"><ntig><termGrp><term>プリンタ</term></termGrp></ntig></langSet>
+    </termEntry>
+    <termEntry id="c2">
+      <langSet xml:lang="de"><tig><term>Drucker</term></tig></langSet>
+    </termEntry>
+  </body></text>
+</martif>
+"#,
+        )
+        .unwrap();
+        let table = read_glossary_table(&tbx_path, &[] as &[&str]).unwrap();
+        assert_eq!(table.languages, vec!["en-us", "ja", "de"]);
+        assert_eq!(
+            table.pairs("en", "ja").unwrap(),
+            vec![("printer".to_string(), "プリンタ".to_string())]
+        );
+        std::fs::remove_file(&tbx_path).unwrap();
+    }
+
+    #[test]
+    fn write_and_read_glossary() {
+        let entries = vec![
+            ("printer".to_string(), "プリンタ".to_string()),
+            ("a, b".to_string(), "A、B".to_string()),
+        ];
+        for ext in ["xlsx", "csv", "tsv"] {
+            let path = std::env::temp_dir().join(format!("cmark_translate_write_glossary.{}", ext));
+            write_glossary(&path, "en", "ja", &entries).unwrap();
+            assert_eq!(read_glossary(&path, "en", "ja").unwrap(), entries);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn check_glossary_issues() {
+        let entries = [
+            ("printer", "プリンタ"),
+            ("printer", "印刷機"),
+            ("Printer", "プリンタ"),
+            ("printer", "プリンタ"),
+            ("e.g.", "例えば"),
+            ("tab\there", "タブ"),
+        ];
+        let issues = check_glossary("en", "ja", &entries);
+        assert_eq!(
+            issues,
+            vec![
+                GlossaryIssue::Punctuation {
+                    term: "e.g.".to_string()
+                },
+                GlossaryIssue::ControlCharacter {
+                    term: "tab\there".to_string()
+                },
+                GlossaryIssue::ConflictingTargets {
+                    term: "printer".to_string(),
+                    targets: vec!["プリンタ".to_string(), "印刷機".to_string()]
+                },
+                GlossaryIssue::CaseOnlyDuplicate {
+                    terms: vec!["Printer".to_string(), "printer".to_string()]
+                },
+            ]
+        );
+        assert!(!issues[0].is_error());
+        assert!(issues[1].is_error());
+
+        let issues = check_glossary("en", "en-gb", &[("color", "colour")]);
+        assert_eq!(
+            issues,
+            vec![GlossaryIssue::UnsupportedLanguagePair {
+                from: "en".to_string(),
+                to: "en-gb".to_string()
+            }]
+        );
+    }
 }
@@ -0,0 +1,362 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Find source files, and map them to output paths
+//!
+use crate::deepl::{Formality, Language};
+use crate::error::{Error, Result};
+
+/// Extensions of CommonMark file, other files are copied as assets
+const CMARK_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+
+/// Placeholders in output path template
+const PLACEHOLDERS: [&str; 5] = ["{path}", "{dir}", "{stem}", "{ext}", "{lang}"];
+
+/// Source file found in input
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceFile {
+    /// Path to read
+    pub path: std::path::PathBuf,
+    /// Path relative to input directory
+    pub relative: std::path::PathBuf,
+}
+
+impl SourceFile {
+    /// Test the file is CommonMark to translate, otherwise asset to copy
+    pub fn is_cmark(&self) -> bool {
+        self.path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                CMARK_EXTENSIONS
+                    .iter()
+                    .any(|cmark_ext| ext.eq_ignore_ascii_case(cmark_ext))
+            })
+    }
+
+    /// Test the file looks like a translation into one of languages, e.g. foo.ja.md
+    ///
+    /// Source language suffix, e.g. Hugo's index.en.md, is not a translation.
+    fn is_translation(&self, from_lang: &str, to_langs: &[&str]) -> bool {
+        self.path
+            .file_stem()
+            .map(std::path::Path::new)
+            .and_then(|stem| stem.extension())
+            .and_then(|lang| lang.to_str())
+            .is_some_and(|lang| {
+                !lang.eq_ignore_ascii_case(from_lang)
+                    && to_langs
+                        .iter()
+                        .any(|to_lang| lang.eq_ignore_ascii_case(to_lang))
+            })
+    }
+}
+
+/// Find source files in input files, directories and glob patterns
+///
+/// Directories are walked recursively, skipping hidden files. Relative path of each file
+/// is taken from the directory, the directory part of the pattern without wildcards,
+/// or the parent of the file. Files found in directories or by patterns which look like
+/// translations into target languages, e.g. foo.ja.md for "ja", are skipped.
+pub fn find_sources<P: AsRef<std::path::Path>>(
+    inputs: &[P],
+    from_lang: &str,
+    to_langs: &[&str],
+) -> Result<Vec<SourceFile>> {
+    let mut sources = vec![];
+    for input in inputs {
+        let input = input.as_ref();
+        let pattern = input.to_string_lossy();
+        if input.is_dir() {
+            // Walk directory
+            let walker = walkdir::WalkDir::new(input)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_entry(|entry| {
+                    entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+                });
+            for entry in walker {
+                let entry = entry.map_err(std::io::Error::from)?;
+                if entry.file_type().is_file() {
+                    push_found(&mut sources, input, entry.into_path(), from_lang, to_langs);
+                }
+            }
+        } else if input.is_file() || !is_pattern(&pattern) {
+            // Single file, error if not found
+            if !input.is_file() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("{:?} NOT found", input),
+                )
+                .into());
+            }
+            sources.push(SourceFile {
+                path: input.to_path_buf(),
+                relative: input.file_name().map(Into::into).unwrap_or_default(),
+            });
+        } else {
+            // Glob pattern
+            let base = input
+                .components()
+                .take_while(|c| !is_pattern(&c.as_os_str().to_string_lossy()))
+                .collect::<std::path::PathBuf>();
+            let paths = glob::glob(&pattern)
+                .map_err(|e| Error::Config(format!("Invalid pattern {:?} : {}", pattern, e)))?;
+            for path in paths {
+                let path = path.map_err(std::io::Error::from)?;
+                if path.is_file() {
+                    push_found(&mut sources, &base, path, from_lang, to_langs);
+                }
+            }
+        }
+    }
+    Ok(sources)
+}
+
+/// Add file found in base directory, unless it looks like a translation
+fn push_found(
+    sources: &mut Vec<SourceFile>,
+    base: &std::path::Path,
+    path: std::path::PathBuf,
+    from_lang: &str,
+    to_langs: &[&str],
+) {
+    let relative = path.strip_prefix(base).unwrap_or(&path).to_path_buf();
+    let source = SourceFile { path, relative };
+    if source.is_translation(from_lang, to_langs) {
+        log::info!("Skip {:?}, looks like translation", source.path);
+    } else {
+        sources.push(source);
+    }
+}
+
+/// Test path contains glob wildcards
+fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Output path template from output argument
+///
+/// Output with placeholders is used as it is. Otherwise, output is the output file
+/// for a single input file, or the output directory which mirrors input tree.
+pub fn output_template<P: AsRef<std::path::Path>>(
+    inputs: &[P],
+    output: &std::path::Path,
+) -> String {
+    let output_str = output.to_string_lossy();
+    let single_file = matches!(inputs, [input] if input.as_ref().is_file());
+    if PLACEHOLDERS.iter().any(|p| output_str.contains(p)) || (single_file && !output.is_dir()) {
+        output_str.into_owned()
+    } else {
+        output.join("{path}").to_string_lossy().into_owned()
+    }
+}
+
+/// Output path of source file from template
+///
+/// Template placeholders are `{path}` relative path of source, `{dir}` its directory,
+/// `{stem}` file name without extension, `{ext}` extension and `{lang}` target language,
+/// e.g. `content/{dir}/{stem}.{lang}.{ext}` for Zola or Hugo, and
+/// `i18n/{lang}/docusaurus-plugin-content-docs/current/{path}` for Docusaurus.
+/// Assets keep their file name in the directory the template points to.
+pub fn output_path(template: &str, source: &SourceFile, lang: &str) -> std::path::PathBuf {
+    let relative = &source.relative;
+    let text = |s: Option<&std::ffi::OsStr>| {
+        s.map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let rendered = template
+        .replace("{path}", &relative.to_string_lossy())
+        .replace(
+            "{dir}",
+            &text(relative.parent().map(std::path::Path::as_os_str)),
+        )
+        .replace("{stem}", &text(relative.file_stem()))
+        .replace("{ext}", &text(relative.extension()))
+        .replace("{lang}", lang);
+
+    // Normalize empty {dir}, e.g. "content//foo.md"
+    let path = std::path::Path::new(&rendered)
+        .components()
+        .collect::<std::path::PathBuf>();
+    match (source.is_cmark(), source.path.file_name()) {
+        (false, Some(file_name)) => path.with_file_name(file_name),
+        _ => path,
+    }
+}
+
+/// Target language and its output path template
+#[derive(Clone)]
+pub struct Target {
+    pub lang: Language,
+    /// Language code for {lang} placeholder, e.g. "ja"
+    pub lang_code: String,
+    pub formality: Formality,
+    pub template: String,
+}
+
+/// Target language, formality and destination of a translation
+pub type Output = (Language, Formality, std::path::PathBuf);
+
+/// Output paths of source files for all targets
+#[derive(Default)]
+pub struct OutputPlan {
+    /// CommonMark files, source and list of (language, formality, destination)
+    pub translations: Vec<(std::path::PathBuf, Vec<Output>)>,
+    /// Non-CommonMark files, source and destination
+    pub assets: Vec<(std::path::PathBuf, std::path::PathBuf)>,
+}
+
+impl OutputPlan {
+    /// Map source files to output paths of each target
+    ///
+    /// Error if CommonMark output overwrites source, or multiple sources are written to
+    /// the same output. Assets mapped to themselves are shared by all targets.
+    pub fn new(sources: &[SourceFile], targets: &[Target]) -> Result<Self> {
+        let mut plan = Self::default();
+        let mut written = std::collections::HashSet::new();
+        for source in sources {
+            let mut outputs = vec![];
+            for target in targets {
+                let dst = output_path(&target.template, source, &target.lang_code);
+                if dst == source.path {
+                    if source.is_cmark() {
+                        return Err(Error::Config(format!("Output {:?} overwrites input", dst)));
+                    }
+                    continue;
+                }
+                if !written.insert(dst.clone()) {
+                    return Err(Error::Config(format!(
+                        "Output {:?} is written more than once, use {{lang}} in output",
+                        dst
+                    )));
+                }
+                if source.is_cmark() {
+                    outputs.push((target.lang, target.formality, dst));
+                } else {
+                    plan.assets.push((source.path.clone(), dst));
+                }
+            }
+            if !outputs.is_empty() {
+                plan.translations.push((source.path.clone(), outputs));
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Keep only outputs which do not exist or are older than source
+    pub fn retain_outdated(&mut self) {
+        for (src, outputs) in &mut self.translations {
+            outputs.retain(|(_, _, dst)| is_outdated(src, dst));
+        }
+        self.translations.retain(|(_, outputs)| !outputs.is_empty());
+        self.assets.retain(|(src, dst)| is_outdated(src, dst));
+    }
+
+    /// Copy assets, creating output directories
+    pub fn copy_assets(&self) -> Result<()> {
+        for (src, dst) in &self.assets {
+            if let Some(dir) = dst.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            log::info!("Copy {:?} to {:?}", src, dst);
+            std::fs::copy(src, dst)?;
+        }
+        Ok(())
+    }
+}
+
+/// Test output does not exist or is older than source
+fn is_outdated(src: &std::path::Path, dst: &std::path::Path) -> bool {
+    let modified = |path: &std::path::Path| std::fs::metadata(path).and_then(|m| m.modified());
+    match (modified(src), modified(dst)) {
+        (Ok(src_time), Ok(dst_time)) => dst_time < src_time,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn map_output_paths() {
+        let root = std::env::temp_dir().join("cmark_translate_layout");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("blog/.hidden")).unwrap();
+        for file in [
+            "_index.md",
+            "_index.ja.md",
+            "about.en.md",
+            "about.de.md",
+            "blog/post.md",
+            "blog/image.png",
+            "blog/.hidden/draft.md",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+
+        // Only translations into target languages are skipped, not of source or others
+        let relatives = find_sources(&[&root], "en", &["ja", "en"])
+            .unwrap()
+            .iter()
+            .map(|s| s.relative.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            relatives,
+            [
+                "_index.md",
+                "about.de.md",
+                "about.en.md",
+                "blog/image.png",
+                "blog/post.md"
+            ]
+        );
+        std::fs::remove_file(root.join("about.en.md")).unwrap();
+        std::fs::remove_file(root.join("about.de.md")).unwrap();
+
+        let sources = find_sources(&[&root], "en", &["ja"]).unwrap();
+        assert_eq!(
+            find_sources(&[root.join("blog/*.md")], "en", &["ja"]).unwrap(),
+            [SourceFile {
+                path: root.join("blog/post.md"),
+                relative: "post.md".into(),
+            }]
+        );
+
+        // Zola, Hugo
+        let template = output_template(
+            &[&root],
+            std::path::Path::new("content/{dir}/{stem}.{lang}.{ext}"),
+        );
+        let outputs = sources
+            .iter()
+            .map(|s| output_path(&template, s, "ja"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outputs,
+            [
+                std::path::Path::new("content/_index.ja.md"),
+                std::path::Path::new("content/blog/image.png"),
+                std::path::Path::new("content/blog/post.ja.md"),
+            ]
+        );
+
+        // Mirror tree
+        let template = output_template(&[&root], std::path::Path::new("i18n/ja"));
+        assert_eq!(
+            output_path(&template, &sources[2], "ja"),
+            std::path::Path::new("i18n/ja/blog/post.md")
+        );
+
+        // Single file
+        let single = [root.join("blog/post.md")];
+        let sources = find_sources(&single, "en", &["ja"]).unwrap();
+        let template = output_template(&single, std::path::Path::new("post.ja.md"));
+        assert_eq!(
+            output_path(&template, &sources[0], "ja"),
+            std::path::Path::new("post.ja.md")
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
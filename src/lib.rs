@@ -1,13 +1,33 @@
 mod cmark_xml;
 mod deepl;
+mod error;
+mod frontmatter;
 mod glossary;
+mod layout;
+mod memory;
+mod project;
 mod trans;
 
 // re-export
 pub use cmark_xml::{
-    cmark_from_xml, cmark_from_xmldom, read_cmark_with_frontmatter, xml_from_cmark,
-    xmldom_from_cmark,
+    cmark_from_xml, cmark_from_xmldom, read_cmark_with_frontmatter,
+    read_cmark_with_frontmatter_format, xml_from_cmark, xmldom_from_cmark, FrontmatterFormat,
+};
+pub use deepl::{
+    glossary_key, Deepl, DeeplGlossary, DeeplGlossaryDictionary, DictionaryEntries, Formality,
+    Language, BASE_URL_ENV,
+};
+pub use error::{Error, Result};
+pub use glossary::{
+    check_glossary, read_glossary, read_glossary_table, write_glossary, GlossaryIssue,
+    GlossaryTable, MAX_GLOSSARY_BYTES, MAX_TERM_BYTES,
+};
+pub use layout::{find_sources, output_path, output_template, OutputPlan, SourceFile, Target};
+pub use memory::{memory_key, TranslationMemory};
+pub use project::{ProjectConfig, TargetConfig, PROJECT_FILE};
+pub use trans::{
+    translate_cmark, translate_cmark_file, translate_cmark_file_langs, translate_cmark_files,
+    translate_frontmatter, translate_json, translate_toml, translate_yaml, update_cmark,
+    update_cmark_file, verify_cmark_file, verify_glossary, GlossaryViolation, TranslateOptions,
+    DEFAULT_FRONTMATTER_KEYS, DEFAULT_MAX_REQUEST_BYTES,
 };
-pub use deepl::{Deepl, DeeplGlossary, Formality, Language};
-pub use glossary::read_glossary;
-pub use trans::{translate_cmark, translate_cmark_file, translate_toml};
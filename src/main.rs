@@ -1,9 +1,11 @@
-mod cmark_xml;
-mod deepl;
-mod glossary;
-mod trans;
-
 use clap::{CommandFactory, Parser};
+use cmark_translate::{
+    check_glossary, find_sources, glossary_key, output_template, read_glossary,
+    read_glossary_table, translate_cmark_file, translate_cmark_file_langs, update_cmark_file,
+    verify_cmark_file, write_glossary, Deepl, DeeplGlossary, DictionaryEntries, Error, Formality,
+    Language, OutputPlan, ProjectConfig, Result, Target, TranslateOptions, TranslationMemory,
+    PROJECT_FILE,
+};
 
 #[derive(clap::Parser)]
 #[command(author, version, about, long_about = None)]
@@ -12,28 +14,69 @@ struct Cli {
     #[arg(short, long, value_name = "FILE")]
     config: Option<std::path::PathBuf>,
 
+    /// Max number of concurrent DeepL requests (default: concurrency in config file)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
 #[derive(clap::Subcommand)]
 enum Commands {
-    /// Translate a CommonMark file
+    /// Translate CommonMark files
+    ///
+    /// Non-CommonMark files found in input directories are copied as assets.
     Translate {
         /// Source language (ISO639-1 2 letter code)
         #[arg(short, long)]
         from: String,
-        /// Target language (ISO639-1 2 letter code)
-        #[arg(short, long)]
-        to: String,
+        /// Target languages (ISO639-1 2 letter code), comma separated
+        ///
+        /// For multiple languages, output should contain {lang} placeholder.
+        #[arg(short, long, required = true, value_delimiter = ',')]
+        to: Vec<String>,
         /// Formality - formal or informal
         #[arg(long)]
         formality: Option<String>,
-        /// Input CommonMark file
-        input: std::path::PathBuf,
-        /// Output translated CommonMark file
+        /// Previous version of single input file, which existing output was translated from
+        ///
+        /// Only blocks changed from it are translated, and manual edits in other
+        /// blocks of output are kept. Input must be one CommonMark file.
+        #[arg(long, value_name = "FILE")]
+        previous: Option<std::path::PathBuf>,
+        /// Translation memory file to reuse translations of unchanged blocks
+        #[arg(long, value_name = "FILE")]
+        memory: Option<std::path::PathBuf>,
+        /// Frontmatter keys to translate, comma separated (default: title,description,extra.time)
+        ///
+        /// Dotted key may contain wildcards, e.g. "extra.*_title", and "**" matches
+        /// any depth, e.g. "extra.**". Strings in arrays are translated by the key of array.
+        #[arg(short = 'k', long, value_name = "KEYS", value_delimiter = ',')]
+        frontmatter_keys: Vec<String>,
+        /// Verify translated terms with glossary file (.xlsx, .csv, .tsv or .tbx)
+        #[arg(long, value_name = "GLOSSARY")]
+        verify_glossary: Option<std::path::PathBuf>,
+        /// Input CommonMark files, directories or glob patterns
+        #[arg(required = true)]
+        input: Vec<std::path::PathBuf>,
+        /// Output file, directory or path template
+        ///
+        /// Template placeholders are {path}, {dir}, {stem}, {ext} and {lang},
+        /// e.g. "content/{dir}/{stem}.{lang}.{ext}" for Zola or Hugo.
         output: std::path::PathBuf,
     },
+    /// Translate outdated files in project config file
+    ///
+    /// Output which does not exist or is older than its source is translated.
+    Sync {
+        /// Project config file (default: cmark-translate.toml)
+        #[arg(short, long, value_name = "FILE")]
+        project: Option<std::path::PathBuf>,
+        /// Translate all files, even if up to date
+        #[arg(long)]
+        force: bool,
+    },
     /// Manage glossaries
     Glossary {
         #[command(subcommand)]
@@ -45,22 +88,87 @@ enum Commands {
 
 #[derive(clap::Subcommand)]
 enum GlossaryCommands {
-    /// Register glossary TSV file
+    /// Register glossary file (.xlsx, .csv, .tsv or .tbx)
+    ///
+    /// Without --from and --to, every language pair in the file is registered.
     Register {
         /// Glossary name
         #[arg(short, long)]
         name: String,
         /// Source language (ISO639-1 2 letter code)
         #[arg(short, long)]
-        from: String,
+        from: Option<String>,
         /// Target language (ISO639-1 2 letter code)
         #[arg(short, long)]
-        to: String,
-        /// Input glossary TSV file - First row should contain language codes
+        to: Option<String>,
+        /// Sheet name of .xlsx to read, can be repeated (default: all sheets)
+        #[arg(short, long)]
+        sheet: Vec<String>,
+        /// Register all language pairs as one v3 multilingual glossary
+        #[arg(short, long)]
+        multilingual: bool,
+        /// Input glossary file - First row should contain language codes
+        input: std::path::PathBuf,
+    },
+    /// Register glossary file and update glossary IDs in config file
+    ///
+    /// Glossaries previously registered in config file are deleted after
+    /// the new ones become ready.
+    Sync {
+        /// Glossary name
+        #[arg(short, long)]
+        name: String,
+        /// Source language (ISO639-1 2 letter code)
+        #[arg(short, long)]
+        from: Option<String>,
+        /// Target language (ISO639-1 2 letter code)
+        #[arg(short, long)]
+        to: Option<String>,
+        /// Sheet name of .xlsx to read, can be repeated (default: all sheets)
+        #[arg(short, long)]
+        sheet: Vec<String>,
+        /// Register all language pairs as one v3 multilingual glossary
+        #[arg(short, long)]
+        multilingual: bool,
+        /// Input glossary file - First row should contain language codes
+        input: std::path::PathBuf,
+    },
+    /// Check glossary file before registration
+    ///
+    /// Exits with error if any problem is found.
+    Check {
+        /// Source language (ISO639-1 2 letter code)
+        #[arg(short, long)]
+        from: Option<String>,
+        /// Target language (ISO639-1 2 letter code)
+        #[arg(short, long)]
+        to: Option<String>,
+        /// Sheet name of .xlsx to read, can be repeated (default: all sheets)
+        #[arg(short, long)]
+        sheet: Vec<String>,
+        /// Treat warnings, e.g. punctuation, case-only duplicates, as errors
+        #[arg(long)]
+        strict: bool,
+        /// Input glossary file - First row should contain language codes
         input: std::path::PathBuf,
     },
     /// List registered glossaries
     List,
+    /// Export entries of registered glossary to .xlsx, .csv or .tsv file
+    ///
+    /// For v3 multilingual glossary, specify language pair by --from and --to.
+    Export {
+        /// Source language (ISO639-1 2 letter code)
+        #[arg(short, long, requires = "to")]
+        from: Option<String>,
+        /// Target language (ISO639-1 2 letter code)
+        #[arg(short, long, requires = "from")]
+        to: Option<String>,
+        /// ID of glossary
+        id: String,
+        /// Output glossary file
+        output: std::path::PathBuf,
+    },
     /// Delete registered glossary
     Delete {
         /// ID of glossary
@@ -69,7 +177,7 @@ enum GlossaryCommands {
 }
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> std::io::Result<()> {
+async fn main() -> Result<()> {
     use std::str::FromStr;
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
 
@@ -77,36 +185,128 @@ async fn main() -> std::io::Result<()> {
     let cli = Cli::parse();
 
     // Load DeepL config
-    let deepl = if let Some(cfg_file) = cli.config {
-        deepl::Deepl::with_config(&cfg_file)
+    let mut deepl = if let Some(cfg_file) = cli.config {
+        Deepl::with_config(&cfg_file)
     } else {
-        deepl::Deepl::new()
+        Deepl::new()
     };
+    if let (Ok(deepl), Some(jobs)) = (&mut deepl, cli.jobs) {
+        deepl.set_concurrency(jobs);
+    }
 
     match cli.command {
         Some(Commands::Translate {
             from,
             to,
             formality,
+            previous,
+            memory,
+            frontmatter_keys,
+            verify_glossary,
             input,
             output,
         }) => {
             // Translate CommonMark file
-            let lang_from = deepl::Language::from_str(&from)?;
-            let lang_to = deepl::Language::from_str(&to)?;
-            let formality = formality.map_or(Ok(deepl::Formality::Default), |f| {
-                deepl::Formality::from_str(&f)
-            })?;
-
-            trans::translate_cmark_file(
-                &deepl.unwrap(),
-                lang_from,
-                lang_to,
-                formality,
-                &input,
-                &output,
-            )
-            .await?;
+            let lang_from = Language::from_str(&from)?;
+            let mut langs_to = vec![];
+            for to in &to {
+                langs_to.push((Language::from_str(to)?, to.to_ascii_lowercase()));
+            }
+            let formality =
+                formality.map_or(Ok(Formality::Default), |f| Formality::from_str(&f))?;
+
+            let deepl = deepl?;
+            let mut options = TranslateOptions::default();
+            if let Some(memory_file) = memory {
+                options.memory = Some(TranslationMemory::open(memory_file)?);
+            }
+            if !frontmatter_keys.is_empty() {
+                options.frontmatter_keys = frontmatter_keys;
+            }
+
+            // Map source files to output paths of each language, copy assets
+            let template = output_template(&input, &output);
+            let targets = langs_to
+                .iter()
+                .map(|(lang_to, lang_code)| Target {
+                    lang: *lang_to,
+                    lang_code: lang_code.clone(),
+                    formality,
+                    template: template.clone(),
+                })
+                .collect::<Vec<_>>();
+            let to_langs = to.iter().map(String::as_str).collect::<Vec<_>>();
+            let plan = OutputPlan::new(&find_sources(&input, &from, &to_langs)?, &targets)?;
+            // Previous version is of one source file, not of directories or glob patterns
+            if previous.is_some()
+                && !(matches!(input.as_slice(), [file] if file.is_file())
+                    && plan.translations.len() == 1)
+            {
+                return Err(Error::Config(String::from(
+                    "--previous can be used only with single input file, not with directories or glob patterns",
+                )));
+            }
+            plan.copy_assets()?;
+
+            let (deepl, options) = (&deepl, &options);
+            let results = match (&previous, plan.translations.as_slice()) {
+                (Some(previous), [(src, outputs)]) => {
+                    // Translate only changed blocks of existing outputs
+                    let results = futures::future::join_all(outputs.iter().map(
+                        |(lang_to, formality, dst)| async move {
+                            if dst.exists() {
+                                update_cmark_file(
+                                    deepl, options, lang_from, *lang_to, *formality, previous, src,
+                                    dst,
+                                )
+                                .await
+                            } else {
+                                log::warn!("{:?} NOT found, translate whole file", dst);
+                                translate_cmark_file(
+                                    deepl, options, lang_from, *lang_to, *formality, src, dst,
+                                )
+                                .await
+                            }
+                        },
+                    ))
+                    .await;
+                    vec![Ok(results)]
+                }
+                _ => translate_plan(deepl, options, lang_from, &plan).await,
+            };
+
+            // Glossary of each target language to verify translated terms
+            let mut glossaries = std::collections::HashMap::new();
+            if let Some(glossary_file) = &verify_glossary {
+                for (lang_to, _) in &langs_to {
+                    let entries = read_glossary(
+                        glossary_file,
+                        lang_from.as_src_langcode(),
+                        lang_to.as_langcode(),
+                    )?;
+                    glossaries.insert(lang_to.as_langcode(), entries);
+                }
+            }
+
+            report_results(options, &plan, results, &glossaries)?;
+        }
+        Some(Commands::Sync { project, force }) => {
+            // Bring all targets in project up to date
+            let project = ProjectConfig::load(project.unwrap_or_else(|| PROJECT_FILE.into()))?;
+            let lang_from = project.source_language()?;
+            let plan = project.plan(force)?;
+
+            let deepl = deepl?;
+            let options = project.options()?;
+
+            plan.copy_assets()?;
+            let results = translate_plan(&deepl, &options, lang_from, &plan).await;
+            report_results(&options, &plan, results, &Default::default())?;
+            eprintln!(
+                "{} files translated, {} assets copied",
+                plan.translations.len(),
+                plan.assets.len()
+            );
         }
         Some(Commands::Glossary { command }) => {
             // Glossary management
@@ -115,42 +315,175 @@ async fn main() -> std::io::Result<()> {
                     name,
                     from,
                     to,
+                    sheet,
+                    multilingual,
                     input,
                 } => {
-                    let from_lang = deepl::Language::from_str(&from)?;
-                    let to_lang = deepl::Language::from_str(&to)?;
-
-                    let glossaries = glossary::read_glossary(
-                        input,
-                        from_lang.as_langcode(),
-                        to_lang.as_langcode(),
-                    )
-                    .unwrap();
-
-                    let glossary = deepl
-                        .unwrap()
-                        .register_glossaries(&name, from_lang, to_lang, &glossaries)
-                        .await
-                        .unwrap();
-                    println!(
-                        "Total {} entries are registered as ID = {}",
-                        glossary.entry_count, glossary.glossary_id
-                    );
+                    let deepl = deepl?;
+                    let selected = select_glossaries(&input, &sheet, from, to)?;
+
+                    let mut registered = vec![];
+                    if multilingual {
+                        // All pairs in one glossary
+                        let glossary = deepl
+                            .register_multilingual_glossary(&name, &dictionaries(&selected))
+                            .await?;
+                        eprintln!(
+                            "Total {} entries are registered as ID = {}",
+                            glossary.entry_count, glossary.glossary_id
+                        );
+                        for (from_lang, to_lang, _) in &selected {
+                            registered.push((
+                                glossary_key(*from_lang, *to_lang),
+                                glossary.glossary_id.clone(),
+                            ));
+                        }
+                    } else {
+                        // One glossary for each pair
+                        for (from_lang, to_lang, glossaries) in selected {
+                            let glossary = deepl
+                                .register_glossaries(&name, from_lang, to_lang, &glossaries)
+                                .await?;
+                            eprintln!(
+                                "{} -> {} : total {} entries are registered as ID = {}",
+                                from_lang.as_src_langcode(),
+                                to_lang.as_langcode(),
+                                glossary.entry_count,
+                                glossary.glossary_id
+                            );
+                            registered
+                                .push((glossary_key(from_lang, to_lang), glossary.glossary_id));
+                        }
+                    }
+
+                    // Print config for deepl.toml
+                    println!("[glossaries]");
+                    for (key, id) in registered {
+                        println!("{} = \"{}\"", key, id);
+                    }
+                }
+                GlossaryCommands::Sync {
+                    name,
+                    from,
+                    to,
+                    sheet,
+                    multilingual,
+                    input,
+                } => {
+                    let mut deepl = deepl?;
+                    let selected = select_glossaries(&input, &sheet, from, to)?;
+                    if multilingual {
+                        sync_multilingual_glossary(&mut deepl, &name, &selected).await?;
+                    } else {
+                        sync_glossaries(&mut deepl, &name, &selected).await?;
+                    }
+                }
+                GlossaryCommands::Check {
+                    from,
+                    to,
+                    sheet,
+                    strict,
+                    input,
+                } => {
+                    let table = read_glossary_table(&input, &sheet)?;
+                    let pairs = if let (Some(from), Some(to)) = (&from, &to) {
+                        // Explicitly specified pair, even if unsupported
+                        vec![(from.clone(), to.clone())]
+                    } else {
+                        table
+                            .language_pairs()
+                            .into_iter()
+                            .filter(|(from_code, to_code)| {
+                                from.as_ref()
+                                    .is_none_or(|f| f.eq_ignore_ascii_case(from_code))
+                                    && to.as_ref().is_none_or(|t| t.eq_ignore_ascii_case(to_code))
+                            })
+                            .map(|(from_code, to_code)| {
+                                (from_code.to_string(), to_code.to_string())
+                            })
+                            .collect::<Vec<_>>()
+                    };
+                    if pairs.is_empty() {
+                        return Err(Error::Glossary(format!(
+                            "No language pair found in {:?}",
+                            input
+                        )));
+                    }
+
+                    let mut errors = 0;
+                    for (from_code, to_code) in pairs {
+                        let glossaries = table.pairs(&from_code, &to_code);
+                        let entries = glossaries.as_deref().unwrap_or_default();
+                        for issue in check_glossary(&from_code, &to_code, entries) {
+                            let is_error = strict || issue.is_error();
+                            println!(
+                                "{} -> {} : {}: {}",
+                                from_code,
+                                to_code,
+                                if is_error { "error" } else { "warning" },
+                                issue
+                            );
+                            if is_error {
+                                errors += 1;
+                            }
+                        }
+                        if glossaries.is_none() {
+                            println!(
+                                "{} -> {} : error: language column NOT found, found {:?}",
+                                from_code, to_code, table.languages
+                            );
+                            errors += 1;
+                        }
+                    }
+                    if 0 < errors {
+                        return Err(Error::Glossary(format!(
+                            "{} errors found in {:?}",
+                            errors, input
+                        )));
+                    }
                 }
                 GlossaryCommands::List => {
                     // List glossaries
-                    let glossaries = deepl.unwrap().list_glossaries().await.unwrap();
+                    let glossaries = deepl?.list_glossaries().await?;
                     for glossary in glossaries {
                         println!("{:?}\n", glossary);
                     }
                 }
+                GlossaryCommands::Export {
+                    from,
+                    to,
+                    id,
+                    output,
+                } => {
+                    let deepl = deepl?;
+                    if let (Some(from), Some(to)) = (from, to) {
+                        // v3 multilingual glossary
+                        let from_lang = Language::from_str(&from)?;
+                        let to_lang = Language::from_str(&to)?;
+                        let entries = deepl
+                            .multilingual_glossary_entries(&id, from_lang, to_lang)
+                            .await?;
+                        write_glossary(&output, &from, &to, &entries)?;
+                    } else {
+                        // v2 glossary
+                        let glossary = deepl.get_glossary(&id).await?;
+                        let entries = deepl.glossary_entries(&id).await?;
+                        write_glossary(
+                            &output,
+                            &glossary.source_lang,
+                            &glossary.target_lang,
+                            &entries,
+                        )?;
+                    }
+                    eprintln!("Glossary {} is exported to {:?}", id, output);
+                }
                 GlossaryCommands::Delete { id } => {
-                    deepl.unwrap().remove_glossary(&id).await.unwrap();
+                    deepl?.remove_glossary(&id).await?;
                 }
             }
         }
         Some(Commands::Usage) => {
-            let used_chars = deepl.unwrap().get_usage().await.unwrap();
+            let used_chars = deepl?.get_usage().await?;
             println!("{} characters used.", used_chars);
         }
         _ => {
@@ -161,3 +494,285 @@ async fn main() -> std::io::Result<()> {
 
     Ok(())
 }
+
+/// Result of each source file, and each target of it
+type PlanResults = Vec<Result<Vec<Result<()>>>>;
+
+/// Translate CommonMark files in plan concurrently
+async fn translate_plan(
+    deepl: &Deepl,
+    options: &TranslateOptions,
+    from_lang: Language,
+    plan: &OutputPlan,
+) -> PlanResults {
+    futures::future::join_all(
+        plan.translations.iter().map(|(src, outputs)| {
+            translate_cmark_file_langs(deepl, options, from_lang, src, outputs)
+        }),
+    )
+    .await
+}
+
+/// Save translation memory, verify glossary terms and report failed files
+///
+/// glossaries are keyed by target language code. Returns the first error if any file failed.
+fn report_results(
+    options: &TranslateOptions,
+    plan: &OutputPlan,
+    results: PlanResults,
+    glossaries: &std::collections::HashMap<&str, Vec<(String, String)>>,
+) -> Result<()> {
+    if let Some(memory) = &options.memory {
+        // Save translations for next run, even if verification fails
+        memory.save()?;
+        eprintln!(
+            "Translation memory : {} blocks reused, {} blocks translated",
+            memory.hits(),
+            memory.misses()
+        );
+    }
+
+    let mut first_err = None;
+    for ((src, outputs), results) in plan.translations.iter().zip(results) {
+        let results = match results {
+            Ok(results) => results,
+            Err(err) => {
+                eprintln!("{} : {}", src.display(), err);
+                first_err.get_or_insert(err);
+                continue;
+            }
+        };
+        for ((lang_to, _, dst), result) in outputs.iter().zip(results) {
+            if let Err(err) = result {
+                eprintln!("{} : {}", dst.display(), err);
+                first_err.get_or_insert(err);
+            } else if let Some(glossaries) = glossaries.get(lang_to.as_langcode()) {
+                // Check glossary terms in translated file
+                let violations = match verify_cmark_file(src, dst, glossaries) {
+                    Ok(violations) => violations,
+                    Err(err) => {
+                        eprintln!("{} : {}", dst.display(), err);
+                        first_err.get_or_insert(err);
+                        continue;
+                    }
+                };
+                for violation in &violations {
+                    println!("{} : {}", dst.display(), violation);
+                }
+                eprintln!(
+                    "{} : {} glossary violation(s)",
+                    dst.display(),
+                    violations.len()
+                );
+            }
+        }
+    }
+    first_err.map_or(Ok(()), Err)
+}
+
+/// Glossary entries of a language pair
+type LanguagePairGlossary = (Language, Language, Vec<(String, String)>);
+
+/// Read glossary file, and pick up language pairs filtered by from and to
+fn select_glossaries(
+    input: &std::path::Path,
+    sheets: &[String],
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<LanguagePairGlossary>> {
+    use std::str::FromStr;
+    let from_filter = from.map(|f| Language::from_str(&f)).transpose()?;
+    let to_filter = to.map(|t| Language::from_str(&t)).transpose()?;
+    let table = read_glossary_table(input, sheets)?;
+
+    let mut selected = vec![];
+    for (from_code, to_code) in table.language_pairs() {
+        let from_lang = Language::from_str(from_code)?;
+        let to_lang = Language::from_str(to_code)?;
+        // Filter by --from, --to
+        if from_filter.is_some_and(|f| f.as_src_langcode() != from_lang.as_src_langcode())
+            || to_filter.is_some_and(|t| t.as_langcode() != to_lang.as_langcode())
+        {
+            continue;
+        }
+
+        let glossaries = table.pairs(from_code, to_code).unwrap_or_default();
+        if glossaries.is_empty() {
+            log::warn!("No entries for {} -> {}", from_code, to_code);
+            continue;
+        }
+        selected.push((from_lang, to_lang, glossaries));
+    }
+    Ok(selected)
+}
+
+/// Borrow selected glossaries as dictionaries of multilingual glossary
+fn dictionaries(selected: &[LanguagePairGlossary]) -> Vec<DictionaryEntries<'_, String>> {
+    selected
+        .iter()
+        .map(|(from_lang, to_lang, glossaries)| (*from_lang, *to_lang, glossaries.as_slice()))
+        .collect()
+}
+
+/// Register glossary for each language pair, update config and delete old ones
+///
+/// Config is saved once after all pairs are registered, so that it never points to
+/// glossary discarded after failure.
+async fn sync_glossaries(
+    deepl: &mut Deepl,
+    name: &str,
+    selected: &[LanguagePairGlossary],
+) -> Result<()> {
+    // Register new ones
+    let mut registered = vec![];
+    for (from_lang, to_lang, glossaries) in selected {
+        let result = register_glossary(deepl, name, *from_lang, *to_lang, glossaries).await;
+        match result {
+            Ok(glossary) => registered.push((*from_lang, *to_lang, glossary)),
+            Err(err) => {
+                for (_, _, glossary) in &registered {
+                    discard_glossary(deepl, &glossary.glossary_id).await;
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    // Replace IDs in config file
+    let mut old_ids = selected
+        .iter()
+        .map(|(from_lang, to_lang, _)| deepl.glossary_id(*from_lang, *to_lang).map(String::from))
+        .collect::<Vec<_>>();
+    old_ids.sort();
+    old_ids.dedup();
+    let ids = registered
+        .iter()
+        .map(|(from_lang, to_lang, glossary)| (*from_lang, *to_lang, glossary.glossary_id.as_str()))
+        .collect::<Vec<_>>();
+    if let Err(err) = deepl.update_glossary_ids(&ids) {
+        for (_, _, glossary) in &registered {
+            discard_glossary(deepl, &glossary.glossary_id).await;
+        }
+        return Err(err);
+    }
+    for (from_lang, to_lang, glossary) in &registered {
+        eprintln!(
+            "{} -> {} : total {} entries are registered as ID = {}, saved to {:?}",
+            from_lang.as_src_langcode(),
+            to_lang.as_langcode(),
+            glossary.entry_count,
+            glossary.glossary_id,
+            deepl.config_path()
+        );
+    }
+
+    // Delete old ones, unless other language pairs still use them
+    for old_id in old_ids.into_iter().flatten() {
+        remove_unused_glossary(deepl, &old_id).await?;
+    }
+    Ok(())
+}
+
+/// Register glossary of language pair and wait until it is ready
+///
+/// Glossary is deleted if it does not get ready.
+async fn register_glossary(
+    deepl: &Deepl,
+    name: &str,
+    from_lang: Language,
+    to_lang: Language,
+    glossaries: &[(String, String)],
+) -> Result<DeeplGlossary> {
+    let glossary = deepl
+        .register_glossaries(name, from_lang, to_lang, glossaries)
+        .await?;
+    let new_id = glossary.glossary_id;
+    let result = async {
+        let glossary = deepl
+            .wait_glossary_ready(&new_id, std::time::Duration::from_secs(60))
+            .await?;
+        if !glossary.ready {
+            return Err(Error::Glossary(format!("Glossary {} is not ready", new_id)));
+        }
+        Ok(glossary)
+    }
+    .await;
+    if result.is_err() {
+        discard_glossary(deepl, &new_id).await;
+    }
+    result
+}
+
+/// Delete glossary no longer used by any language pair in config
+///
+/// Old glossary may be multilingual one, so v3 endpoint is used.
+async fn remove_unused_glossary(deepl: &Deepl, id: &str) -> Result<()> {
+    if !deepl.glossary_in_use(id) {
+        deepl.delete_multilingual_glossary(id).await?;
+        eprintln!("Old glossary {} is deleted", id);
+    }
+    Ok(())
+}
+
+/// Delete newly registered glossary after sync failed, not to leave it unused
+async fn discard_glossary(deepl: &Deepl, id: &str) {
+    if let Err(err) = deepl.delete_multilingual_glossary(id).await {
+        log::warn!("New glossary {} can not be deleted : {}", id, err);
+    }
+}
+
+/// Update v3 multilingual glossary in place, or register new one and update config
+async fn sync_multilingual_glossary(
+    deepl: &mut Deepl,
+    name: &str,
+    selected: &[LanguagePairGlossary],
+) -> Result<()> {
+    // Glossary IDs currently used for the pairs
+    let mut old_ids = selected
+        .iter()
+        .map(|(from_lang, to_lang, _)| deepl.glossary_id(*from_lang, *to_lang).map(String::from))
+        .collect::<Vec<_>>();
+    old_ids.sort();
+    old_ids.dedup();
+
+    if let [Some(old_id)] = old_ids.as_slice() {
+        // All pairs are in the same glossary, edit it in place
+        if deepl.get_multilingual_glossary(old_id).await.is_ok() {
+            for (from_lang, to_lang, glossaries) in selected {
+                let dict = deepl
+                    .replace_glossary_dictionary(old_id, *from_lang, *to_lang, glossaries)
+                    .await?;
+                eprintln!(
+                    "{} -> {} : total {} entries are updated in ID = {}",
+                    dict.source_lang, dict.target_lang, dict.entry_count, old_id
+                );
+            }
+            return Ok(());
+        }
+    }
+
+    // Register new one
+    let glossary = deepl
+        .register_multilingual_glossary(name, &dictionaries(selected))
+        .await?;
+    let ids = selected
+        .iter()
+        .map(|(from_lang, to_lang, _)| (*from_lang, *to_lang, glossary.glossary_id.as_str()))
+        .collect::<Vec<_>>();
+    if let Err(err) = deepl.update_glossary_ids(&ids) {
+        discard_glossary(deepl, &glossary.glossary_id).await;
+        return Err(err);
+    }
+    eprintln!(
+        "Total {} entries are registered as ID = {}, saved to {:?}",
+        glossary.entry_count,
+        glossary.glossary_id,
+        deepl.config_path()
+    );
+
+    // Delete old ones no longer used
+    for old_id in old_ids.into_iter().flatten() {
+        remove_unused_glossary(deepl, &old_id).await?;
+    }
+    Ok(())
+}
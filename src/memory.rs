@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Translation memory, reuse translations of unchanged blocks
+//!
+use crate::deepl::{Formality, Language};
+use crate::error::{Error, Result};
+
+/// File based store of translated blocks
+///
+/// Keyed by hash of source text, language pair, formality and glossary ID.
+pub struct TranslationMemory {
+    path: std::path::PathBuf,
+    entries: std::sync::Mutex<std::collections::BTreeMap<String, String>>,
+    modified: std::sync::atomic::AtomicBool,
+    hits: std::sync::atomic::AtomicUsize,
+    misses: std::sync::atomic::AtomicUsize,
+}
+
+impl TranslationMemory {
+    /// Open translation memory file, empty one if the file does not exist
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let entries = match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| Error::Memory(format!("Can not parse {:?} : {}", path, e)))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                log::debug!("Translation memory {:?} NOT found, create new one", path);
+                Default::default()
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries: std::sync::Mutex::new(entries),
+            modified: Default::default(),
+            hits: Default::default(),
+            misses: Default::default(),
+        })
+    }
+
+    /// Save to file if modified
+    pub fn save(&self) -> Result<()> {
+        use std::sync::atomic::Ordering;
+        if !self.modified.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let json = serde_json::to_string_pretty(&*self.lock())
+            .map_err(|e| Error::Memory(e.to_string()))?;
+        std::fs::write(&self.path, json)?;
+        self.modified.store(false, Ordering::Relaxed);
+        log::debug!("Save translation memory {:?}", self.path);
+        Ok(())
+    }
+
+    /// Find translation, counted as hit or miss
+    pub fn get(&self, key: &str) -> Option<String> {
+        use std::sync::atomic::Ordering;
+        let translated = self.lock().get(key).cloned();
+        if translated.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        translated
+    }
+
+    /// Store translation
+    pub fn insert(&self, key: String, translated: String) {
+        self.lock().insert(key, translated);
+        self.modified
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Number of stored translations
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Test no translation is stored
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Number of translations found in memory
+    pub fn hits(&self) -> usize {
+        self.hits.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Number of translations NOT found in memory
+    pub fn misses(&self) -> usize {
+        self.misses.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, std::collections::BTreeMap<String, String>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Key of translation memory, SHA-256 of source text and translation options
+pub fn memory_key(
+    from_lang: Language,
+    to_lang: Language,
+    formality: Formality,
+    glossary_id: Option<&str>,
+    source: &str,
+) -> String {
+    use sha2::Digest;
+    let mut hasher = sha2::Sha256::new();
+    for item in [
+        from_lang.as_src_langcode(),
+        to_lang.as_langcode(),
+        formality.to_str(),
+        glossary_id.unwrap_or_default(),
+        source,
+    ] {
+        // Length prefix avoids ambiguity of concatenation
+        hasher.update((item.len() as u64).to_le_bytes());
+        hasher.update(item.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn save_and_open_memory() {
+        let path = std::env::temp_dir().join("cmark_translate_memory.json");
+        let _ = std::fs::remove_file(&path);
+
+        let key = memory_key(
+            Language::En,
+            Language::Ja,
+            Formality::Default,
+            None,
+            "<p>Hello</p>",
+        );
+        assert_ne!(
+            key,
+            memory_key(
+                Language::En,
+                Language::Ja,
+                Formality::Formal,
+                None,
+                "<p>Hello</p>"
+            )
+        );
+        assert_ne!(
+            key,
+            memory_key(
+                Language::En,
+                Language::Ja,
+                Formality::Default,
+                Some("glossary"),
+                "<p>Hello</p>"
+            )
+        );
+
+        let memory = TranslationMemory::open(&path).unwrap();
+        assert!(memory.is_empty());
+        assert_eq!(memory.get(&key), None);
+        memory.insert(key.clone(), String::from("<p>こんにちは</p>"));
+        memory.save().unwrap();
+
+        let memory = TranslationMemory::open(&path).unwrap();
+        assert_eq!(memory.get(&key).as_deref(), Some("<p>こんにちは</p>"));
+        assert_eq!((memory.hits(), memory.misses()), (1, 0));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Project config file, cmark-translate.toml
+//!
+use crate::deepl::{Formality, Language};
+use crate::error::{Error, Result};
+use crate::layout::{self, OutputPlan, SourceFile, Target};
+use crate::memory::TranslationMemory;
+use crate::trans::TranslateOptions;
+
+/// Default project config file name
+pub const PROJECT_FILE: &str = "cmark-translate.toml";
+
+/// Sources, targets and languages of translation
+///
+/// Relative paths and patterns are relative to the directory of the project file.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ProjectConfig {
+    /// Source language
+    pub from: String,
+    /// Source files, directories or glob patterns
+    pub sources: Vec<String>,
+    /// Glob patterns of files to skip
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Output path template, e.g. "content/{dir}/{stem}.{lang}.{ext}"
+    pub output: String,
+    /// Formality of targets without formality
+    #[serde(default)]
+    pub formality: Option<String>,
+    /// Dotted keys or patterns of frontmatter to translate (default: DEFAULT_FRONTMATTER_KEYS)
+    #[serde(default)]
+    pub frontmatter_keys: Option<Vec<String>>,
+    /// Translation memory file
+    #[serde(default)]
+    pub translation_memory: Option<std::path::PathBuf>,
+    /// Upper limit of URL encoded XML text in a request (default: DEFAULT_MAX_REQUEST_BYTES)
+    #[serde(default)]
+    pub max_request_bytes: Option<usize>,
+    /// Target languages, keyed by language code
+    pub targets: std::collections::BTreeMap<String, TargetConfig>,
+    /// Directory of project file, set after reading the file
+    #[serde(skip)]
+    base_dir: std::path::PathBuf,
+}
+
+/// Options of a target language
+#[derive(Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TargetConfig {
+    /// Formality - formal or informal
+    #[serde(default)]
+    pub formality: Option<String>,
+    /// Output path template, overriding project output
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+impl ProjectConfig {
+    /// Read project config file
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let toml_str = std::fs::read_to_string(path)?;
+        let mut config: Self = toml::from_str(&toml_str)
+            .map_err(|e| Error::Config(format!("Can not parse {:?} : {}", path, e)))?;
+        config.base_dir = path.parent().map(Into::into).unwrap_or_default();
+        Ok(config)
+    }
+
+    /// Source language
+    pub fn source_language(&self) -> Result<Language> {
+        self.from.parse()
+    }
+
+    /// Translation memory file path
+    pub fn translation_memory_path(&self) -> Option<std::path::PathBuf> {
+        self.translation_memory
+            .as_ref()
+            .map(|path| self.base_dir.join(path))
+    }
+
+    /// Options of translation, opening translation memory
+    pub fn options(&self) -> Result<TranslateOptions> {
+        let mut options = TranslateOptions::default();
+        if let Some(keys) = &self.frontmatter_keys {
+            options.frontmatter_keys = keys.clone();
+        }
+        if let Some(memory_file) = self.translation_memory_path() {
+            options.memory = Some(TranslationMemory::open(memory_file)?);
+        }
+        if let Some(max_request_bytes) = self.max_request_bytes {
+            options.max_request_bytes = max_request_bytes;
+        }
+        Ok(options)
+    }
+
+    /// Source files, except excluded ones
+    pub fn sources(&self) -> Result<Vec<SourceFile>> {
+        let inputs = self
+            .sources
+            .iter()
+            .map(|source| self.base_dir.join(source))
+            .collect::<Vec<_>>();
+        let excludes = self
+            .exclude
+            .iter()
+            .map(|pattern| {
+                let pattern = self.base_dir.join(pattern);
+                glob::Pattern::new(&pattern.to_string_lossy()).map_err(|e| {
+                    Error::Config(format!("Invalid exclude pattern {:?} : {}", pattern, e))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let to_langs = self.targets.keys().map(String::as_str).collect::<Vec<_>>();
+        let mut sources = layout::find_sources(&inputs, &self.from, &to_langs)?;
+        sources.retain(|source| !excludes.iter().any(|p| p.matches_path(&source.path)));
+        Ok(sources)
+    }
+
+    /// Target languages with formality and output path template
+    pub fn targets(&self) -> Result<Vec<Target>> {
+        self.targets
+            .iter()
+            .map(|(lang_code, target)| {
+                let formality = target.formality.as_ref().or(self.formality.as_ref());
+                let template = target.output.as_ref().unwrap_or(&self.output);
+                Ok(Target {
+                    lang: lang_code.parse()?,
+                    lang_code: lang_code.to_ascii_lowercase(),
+                    formality: formality.map_or(Ok(Formality::Default), |f| f.parse())?,
+                    template: self.base_dir.join(template).to_string_lossy().into_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Output paths of all sources and targets
+    ///
+    /// Unless force is set, outputs newer than source are skipped.
+    pub fn plan(&self, force: bool) -> Result<OutputPlan> {
+        let mut plan = OutputPlan::new(&self.sources()?, &self.targets()?)?;
+        if !force {
+            plan.retain_outdated();
+        }
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plan_project() {
+        let root = std::env::temp_dir().join("cmark_translate_project");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("content/drafts")).unwrap();
+        for file in [
+            "content/index.md",
+            "content/about.md",
+            "content/drafts/wip.md",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        let project_file = root.join(PROJECT_FILE);
+        std::fs::write(
+            &project_file,
+            r#"
+from = "en"
+sources = ["content"]
+exclude = ["content/drafts/*"]
+output = "content/{dir}/{stem}.{lang}.{ext}"
+formality = "formal"
+max_request_bytes = 1000
+
+[targets]
+ja = {}
+de = { formality = "informal", output = "de/{path}" }
+"#,
+        )
+        .unwrap();
+
+        let project = ProjectConfig::load(&project_file).unwrap();
+        let targets = project.targets().unwrap();
+        assert_eq!(
+            targets
+                .iter()
+                .map(|t| t.lang_code.as_str())
+                .collect::<Vec<_>>(),
+            ["de", "ja"]
+        );
+        assert!(matches!(targets[0].formality, Formality::Informal));
+        assert!(matches!(targets[1].formality, Formality::Formal));
+        let options = project.options().unwrap();
+        assert_eq!(options.max_request_bytes, 1000);
+        assert_eq!(
+            options.frontmatter_keys,
+            crate::trans::DEFAULT_FRONTMATTER_KEYS
+        );
+
+        let plan = project.plan(false).unwrap();
+        let outputs = plan
+            .translations
+            .iter()
+            .flat_map(|(_, outputs)| outputs.iter().map(|(_, _, dst)| dst.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outputs,
+            [
+                root.join("de/about.md"),
+                root.join("content/about.ja.md"),
+                root.join("de/index.md"),
+                root.join("content/index.ja.md"),
+            ]
+        );
+
+        // Up to date outputs are skipped
+        std::fs::write(root.join("content/about.ja.md"), "").unwrap();
+        let plan = project.plan(false).unwrap();
+        assert_eq!(plan.translations[0].1.len(), 1);
+        let plan = project.plan(true).unwrap();
+        assert_eq!(plan.translations[0].1.len(), 2);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,146 +1,1016 @@
 // SPDX-License-Identifier: MIT
-use crate::{cmark_xml, deepl};
+use crate::error::{Error, Result};
+use crate::{cmark_xml, deepl, frontmatter, memory};
+
+/// Frontmatter keys translated by default
+pub const DEFAULT_FRONTMATTER_KEYS: [&str; 3] = ["title", "description", "extra.time"];
+
+/// Upper limit of URL encoded XML text in a translation request by default
+///
+/// DeepL accepts request body up to 128KiB, leave margin for other parameters.
+pub const DEFAULT_MAX_REQUEST_BYTES: usize = 120 * 1024;
+
+/// Options of translating documents, independent of DeepL connection
+pub struct TranslateOptions {
+    /// Dotted keys or patterns of frontmatter to translate, e.g. "extra.*_title"
+    pub frontmatter_keys: Vec<String>,
+    /// Translation memory consulted for each block
+    pub memory: Option<memory::TranslationMemory>,
+    /// Upper limit of URL encoded XML text in a translation request, documents are split by blocks
+    pub max_request_bytes: usize,
+}
+
+impl Default for TranslateOptions {
+    fn default() -> Self {
+        Self {
+            frontmatter_keys: DEFAULT_FRONTMATTER_KEYS.map(String::from).to_vec(),
+            memory: None,
+            max_request_bytes: DEFAULT_MAX_REQUEST_BYTES,
+        }
+    }
+}
 
 /// Translate CommonMark .md file
 pub async fn translate_cmark_file<P: AsRef<std::path::Path>>(
     deepl: &deepl::Deepl,
+    options: &TranslateOptions,
     from_lang: deepl::Language,
     to_lang: deepl::Language,
     formality: deepl::Formality,
     src_path: P,
     dst_path: P,
-) -> std::io::Result<()> {
-    use std::io::Write;
+) -> Result<()> {
+    let mut results = translate_cmark_file_langs(
+        deepl,
+        options,
+        from_lang,
+        src_path,
+        &[(to_lang, formality, dst_path)],
+    )
+    .await?;
+    results.remove(0)
+}
 
+/// Translate CommonMark .md file into multiple languages
+///
+/// Source file is read and parsed once. outputs are list of
+/// (target language, formality, destination) and result of each is returned in the same order.
+pub async fn translate_cmark_file_langs<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    deepl: &deepl::Deepl,
+    options: &TranslateOptions,
+    from_lang: deepl::Language,
+    src_path: P,
+    outputs: &[(deepl::Language, deepl::Formality, Q)],
+) -> Result<Vec<Result<()>>> {
     // Read .md file
     let mut f = std::fs::File::open(src_path)?;
-    let (cmark_text, frontmatter) = cmark_xml::read_cmark_with_frontmatter(&mut f)?;
+    let (cmark_text, frontmatter) = cmark_xml::read_cmark_with_frontmatter_format(&mut f)?;
     drop(f);
 
-    log::trace!(
-        "Read file:\n+++\n{}\n+++\n{}",
-        frontmatter.as_deref().unwrap_or_default(),
-        cmark_text
-    );
+    log::trace!("Read file:\n{:?}\n{}", frontmatter, cmark_text);
+    let xml_root = cmark_xml::xmldom_from_cmark(&cmark_text, true);
 
-    // Parse frontmatter
-    let translated_frontmatter = if let Some(frontmatter) = frontmatter {
-        // translate TOML frontmatter
-        Some(translate_toml(&deepl, from_lang, to_lang, formality, &frontmatter).await?)
-    } else {
-        None
+    // Translate into each language concurrently
+    let (xml_root, frontmatter) = (&xml_root, &frontmatter);
+    let results = futures::future::join_all(outputs.iter().map(
+        |(to_lang, formality, dst_path)| async move {
+            // Parse frontmatter
+            let translated_frontmatter = if let Some((format, frontmatter)) = frontmatter {
+                // translate frontmatter, keeping its format
+                let translated = translate_frontmatter(
+                    deepl,
+                    options,
+                    from_lang,
+                    *to_lang,
+                    *formality,
+                    *format,
+                    frontmatter,
+                )
+                .await?;
+                Some((*format, translated))
+            } else {
+                None
+            };
+
+            // Translate CommonMark body
+            let translated_cmark =
+                translate_xmldom(deepl, options, from_lang, *to_lang, *formality, xml_root).await?;
+
+            // Print result
+            write_cmark_file(dst_path, translated_frontmatter.as_ref(), &translated_cmark)
+        },
+    ))
+    .await;
+    Ok(results)
+}
+
+/// Update existing translated CommonMark .md file for changed source
+///
+/// prev_src_path is the source file which dst_path was translated from.
+/// Only blocks changed from prev_src_path are translated, and other blocks in dst_path
+/// are kept as they are, including manual edits.
+#[allow(clippy::too_many_arguments)]
+pub async fn update_cmark_file<P: AsRef<std::path::Path>>(
+    deepl: &deepl::Deepl,
+    options: &TranslateOptions,
+    from_lang: deepl::Language,
+    to_lang: deepl::Language,
+    formality: deepl::Formality,
+    prev_src_path: P,
+    src_path: P,
+    dst_path: P,
+) -> Result<()> {
+    let read = |path: &std::path::Path| {
+        let mut f = std::fs::File::open(path)?;
+        cmark_xml::read_cmark_with_frontmatter_format(&mut f)
     };
+    let (prev_cmark, prev_frontmatter) = read(prev_src_path.as_ref())?;
+    let (cmark_text, frontmatter) = read(src_path.as_ref())?;
+    let (translated_cmark, translated_frontmatter) = read(dst_path.as_ref())?;
 
-    // Translate CommonMark body
-    let translated_cmark =
-        translate_cmark(&deepl, from_lang, to_lang, formality, &cmark_text).await?;
+    // Keep translated frontmatter if source is not changed
+    let translated_frontmatter = match frontmatter {
+        Some(frontmatter)
+            if prev_frontmatter.as_ref() != Some(&frontmatter)
+                || translated_frontmatter.is_none() =>
+        {
+            let (format, frontmatter) = frontmatter;
+            let translated = translate_frontmatter(
+                deepl,
+                options,
+                from_lang,
+                to_lang,
+                formality,
+                format,
+                &frontmatter,
+            )
+            .await?;
+            Some((format, translated))
+        }
+        Some(_) => translated_frontmatter,
+        None => None,
+    };
+
+    let updated_cmark = update_cmark(
+        deepl,
+        options,
+        from_lang,
+        to_lang,
+        formality,
+        &prev_cmark,
+        &cmark_text,
+        &translated_cmark,
+    )
+    .await?;
 
-    // Print result
+    write_cmark_file(dst_path, translated_frontmatter.as_ref(), &updated_cmark)
+}
+
+/// Write CommonMark .md file with frontmatter between its delimiters
+fn write_cmark_file<P: AsRef<std::path::Path>>(
+    dst_path: P,
+    frontmatter: Option<&(cmark_xml::FrontmatterFormat, String)>,
+    cmark_text: &str,
+) -> Result<()> {
+    use std::io::Write;
+
+    if let Some(dir) = dst_path.as_ref().parent() {
+        std::fs::create_dir_all(dir)?;
+    }
     let mut f = std::fs::File::create(dst_path)?;
-    if let Some(translated_frontmatter) = translated_frontmatter {
-        f.write_all("+++\n".as_bytes())?;
-        f.write_all(translated_frontmatter.as_bytes())?;
-        f.write_all("+++\n".as_bytes())?;
+    match frontmatter.map(|(format, frontmatter)| (format.delimiter(), frontmatter)) {
+        Some((Some(delimiter), frontmatter)) => {
+            let delimiter = format!("{}\n", delimiter);
+            f.write_all(delimiter.as_bytes())?;
+            f.write_all(frontmatter.as_bytes())?;
+            f.write_all(delimiter.as_bytes())?;
+        }
+        Some((None, frontmatter)) => {
+            // JSON object followed by its line breaks
+            f.write_all(frontmatter.as_bytes())?;
+            if !frontmatter.ends_with('\n') {
+                f.write_all("\n".as_bytes())?;
+            }
+        }
+        None => {}
     }
-    f.write_all(translated_cmark.as_bytes())?;
+    f.write_all(cmark_text.as_bytes())?;
     Ok(())
 }
 
+/// Translate multiple CommonMark .md files concurrently
+///
+/// files are list of (source, destination) paths. Returns result of each file in the same order.
+/// Number of concurrent requests is limited by Deepl.
+pub async fn translate_cmark_files<P: AsRef<std::path::Path>>(
+    deepl: &deepl::Deepl,
+    options: &TranslateOptions,
+    from_lang: deepl::Language,
+    to_lang: deepl::Language,
+    formality: deepl::Formality,
+    files: &[(P, P)],
+) -> Vec<Result<()>> {
+    futures::future::join_all(files.iter().map(|(src_path, dst_path)| {
+        translate_cmark_file(
+            deepl,
+            options,
+            from_lang,
+            to_lang,
+            formality,
+            src_path.as_ref(),
+            dst_path.as_ref(),
+        )
+    }))
+    .await
+}
+
+/// Translate frontmatter in its format
+pub async fn translate_frontmatter(
+    deepl: &deepl::Deepl,
+    options: &TranslateOptions,
+    from_lang: deepl::Language,
+    to_lang: deepl::Language,
+    formality: deepl::Formality,
+    format: cmark_xml::FrontmatterFormat,
+    frontmatter: &str,
+) -> Result<String> {
+    match format {
+        cmark_xml::FrontmatterFormat::Toml => {
+            translate_toml(deepl, options, from_lang, to_lang, formality, frontmatter).await
+        }
+        cmark_xml::FrontmatterFormat::Yaml => {
+            translate_yaml(deepl, options, from_lang, to_lang, formality, frontmatter).await
+        }
+        cmark_xml::FrontmatterFormat::Json => {
+            translate_json(deepl, options, from_lang, to_lang, formality, frontmatter).await
+        }
+    }
+}
+
 /// Translate TOML frontmatter
+///
+/// Only translated string values are replaced, and comments and formatting are preserved.
 pub async fn translate_toml(
     deepl: &deepl::Deepl,
+    options: &TranslateOptions,
     from_lang: deepl::Language,
     to_lang: deepl::Language,
     formality: deepl::Formality,
     toml_frontmatter: &str,
-) -> Result<String, std::io::Error> {
-    match toml_frontmatter.parse::<toml::Value>() {
-        Ok(toml::Value::Table(mut root)) => {
-            // Pickup TOML key for translation
-            let mut should_be_translate: Vec<&mut String> = vec![];
-            for (key, val) in &mut root {
-                match key.as_str() {
-                    "title" | "description" => {
-                        if let toml::Value::String(val) = val {
-                            should_be_translate.push(val);
-                        }
-                    }
-                    "extra" => {
-                        if let toml::Value::Table(extra) = val {
-                            for (extra_key, extra_val) in extra {
-                                match extra_key.as_str() {
-                                    "time" => {
-                                        if let toml::Value::String(extra_val) = extra_val {
-                                            should_be_translate.push(extra_val);
-                                        }
-                                    }
-                                    _ => (),
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                }
-            }
+) -> Result<String> {
+    let mut doc = toml_frontmatter
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(|e| Error::Frontmatter(e.to_string()))?;
 
-            // Prepare input Vec
-            let src_vec = should_be_translate
-                .iter()
-                .map(|s| s.as_str())
-                .collect::<Vec<&str>>();
-            // Translate texts
-            let translated_vec = deepl
-                .translate_strings(from_lang, to_lang, formality, &src_vec)
-                .await
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-
-            // Replace TOML value with translated text
-            should_be_translate
-                .into_iter()
-                .zip(translated_vec.iter())
-                .for_each(|(toml_val, translated_str)| {
-                    toml_val.clear();
-                    *toml_val += translated_str.as_str();
-                });
+    // Pickup TOML key for translation
+    let mut values = vec![];
+    toml_strings(
+        &options.frontmatter_keys,
+        "",
+        doc.as_item_mut(),
+        &mut values,
+    );
+    let mut texts = values
+        .iter()
+        .map(|val| val.as_str().unwrap_or_default().to_string())
+        .collect::<Vec<_>>();
+    translate_in_place(
+        deepl,
+        from_lang,
+        to_lang,
+        formality,
+        texts.iter_mut().collect(),
+    )
+    .await?;
+
+    // Replace TOML value with translated text, keeping spaces and comments around it
+    for (val, text) in values.into_iter().zip(texts) {
+        let decor = val.decor().clone();
+        *val = toml_edit::Value::from(text);
+        *val.decor_mut() = decor;
+    }
+
+    let translated_frontmatter = doc.to_string();
+    // Show translated frontmatter
+    log::trace!("Translated TOML :\n{}\n", translated_frontmatter);
+
+    Ok(translated_frontmatter)
+}
+
+/// Translate YAML frontmatter
+///
+/// Only translated scalars are replaced, and comments, anchors and quoting are preserved.
+pub async fn translate_yaml(
+    deepl: &deepl::Deepl,
+    options: &TranslateOptions,
+    from_lang: deepl::Language,
+    to_lang: deepl::Language,
+    formality: deepl::Formality,
+    yaml_frontmatter: &str,
+) -> Result<String> {
+    // Pickup YAML key for translation
+    let keys = &options.frontmatter_keys;
+    let mut scalars = vec![];
+    let mut texts = vec![];
+    for (key, scalar) in frontmatter::YamlScanner::new(yaml_frontmatter).strings()? {
+        if is_frontmatter_key(keys, &key) {
+            texts.push(scalar.value(yaml_frontmatter));
+            scalars.push(scalar);
+        }
+    }
+    translate_in_place(
+        deepl,
+        from_lang,
+        to_lang,
+        formality,
+        texts.iter_mut().collect(),
+    )
+    .await?;
+
+    // Replace scalars from the last one, so that earlier spans are kept valid
+    let mut translated_frontmatter = yaml_frontmatter.to_string();
+    for (scalar, text) in scalars.into_iter().zip(texts).rev() {
+        translated_frontmatter.replace_range(scalar.span.clone(), &scalar.literal(&text));
+    }
+    log::trace!("Translated YAML :\n{}\n", translated_frontmatter);
+
+    Ok(translated_frontmatter)
+}
+
+/// Translate JSON frontmatter
+///
+/// Only translated string literals are replaced, and formatting is preserved.
+pub async fn translate_json(
+    deepl: &deepl::Deepl,
+    options: &TranslateOptions,
+    from_lang: deepl::Language,
+    to_lang: deepl::Language,
+    formality: deepl::Formality,
+    json_frontmatter: &str,
+) -> Result<String> {
+    let json_error = |e: serde_json::Error| Error::Frontmatter(e.to_string());
+    if !serde_json::from_str::<serde_json::Value>(json_frontmatter)
+        .map_err(json_error)?
+        .is_object()
+    {
+        return Err(Error::Frontmatter(String::from(
+            "JSON root is not an object",
+        )));
+    }
+
+    // Pickup JSON key for translation
+    let keys = &options.frontmatter_keys;
+    let mut spans = vec![];
+    let mut texts = vec![];
+    for (key, span) in frontmatter::JsonScanner::new(json_frontmatter).strings()? {
+        if is_frontmatter_key(keys, &key) {
+            texts.push(
+                serde_json::from_str::<String>(&json_frontmatter[span.clone()])
+                    .map_err(json_error)?,
+            );
+            spans.push(span);
+        }
+    }
+    translate_in_place(
+        deepl,
+        from_lang,
+        to_lang,
+        formality,
+        texts.iter_mut().collect(),
+    )
+    .await?;
+
+    // Replace string literals from the last one, so that earlier spans are kept valid
+    let mut translated_frontmatter = json_frontmatter.to_string();
+    for (span, text) in spans.into_iter().zip(texts).rev() {
+        translated_frontmatter
+            .replace_range(span, &serde_json::to_string(&text).map_err(json_error)?);
+    }
+    log::trace!("Translated JSON :\n{}\n", translated_frontmatter);
 
-            // Serialize toml::Value should not fail
-            let translated_frontmatter = toml::to_string_pretty(&toml::Value::Table(root)).unwrap();
-            // Show translated frontmatter
-            log::trace!("Translated TOML :\n{}\n", translated_frontmatter);
+    Ok(translated_frontmatter)
+}
+
+/// Translate texts, and replace them with translated ones
+async fn translate_in_place(
+    deepl: &deepl::Deepl,
+    from_lang: deepl::Language,
+    to_lang: deepl::Language,
+    formality: deepl::Formality,
+    texts: Vec<&mut String>,
+) -> Result<()> {
+    if texts.is_empty() {
+        return Ok(());
+    }
+    let src_vec = texts.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
+    let translated_vec = deepl
+        .translate_strings(from_lang, to_lang, formality, &src_vec)
+        .await?;
+    for (text, translated) in texts.into_iter().zip(translated_vec) {
+        *text = translated;
+    }
+    Ok(())
+}
+
+/// Test dotted key matches any of frontmatter key patterns
+///
+/// Each dot separated part of pattern may contain wildcards, e.g. "extra.*_title",
+/// and "**" part matches any number of parts, e.g. "extra.**".
+/// Strings in arrays have the key of the array, e.g. "tags".
+fn is_frontmatter_key(patterns: &[String], key: &str) -> bool {
+    let key_parts = key.split('.').collect::<Vec<_>>();
+    patterns.iter().any(|pattern| {
+        let pattern_parts = pattern.split('.').collect::<Vec<_>>();
+        key_parts_match(&pattern_parts, &key_parts)
+    })
+}
+
+/// Test key parts match pattern parts
+fn key_parts_match(pattern: &[&str], key: &[&str]) -> bool {
+    match (pattern.split_first(), key.split_first()) {
+        (Some((&"**", pattern_rest)), _) => {
+            key_parts_match(pattern_rest, key)
+                || (!key.is_empty() && key_parts_match(pattern, &key[1..]))
+        }
+        (Some((pattern_part, pattern_rest)), Some((key_part, key_rest))) => {
+            let part_matches = glob::Pattern::new(pattern_part)
+                .map_or(pattern_part == key_part, |p| p.matches(key_part));
+            part_matches && key_parts_match(pattern_rest, key_rest)
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
 
-            Ok(translated_frontmatter)
+/// Collect TOML string values of frontmatter keys
+fn toml_strings<'a>(
+    keys: &[String],
+    key: &str,
+    item: &'a mut toml_edit::Item,
+    strings: &mut Vec<&'a mut toml_edit::Value>,
+) {
+    match item {
+        toml_edit::Item::Value(val) => toml_value_strings(keys, key, val, strings),
+        toml_edit::Item::Table(table) => {
+            for (child, item) in table.iter_mut() {
+                toml_strings(
+                    keys,
+                    &frontmatter::child_key(key, child.get()),
+                    item,
+                    strings,
+                );
+            }
+        }
+        toml_edit::Item::ArrayOfTables(tables) => {
+            // Tables have the key of array
+            for table in tables.iter_mut() {
+                for (child, item) in table.iter_mut() {
+                    toml_strings(
+                        keys,
+                        &frontmatter::child_key(key, child.get()),
+                        item,
+                        strings,
+                    );
+                }
+            }
         }
-        Ok(_) => {
-            // TOML parse failed
-            Err(std::io::Error::from(std::io::ErrorKind::InvalidData))
+        toml_edit::Item::None => {}
+    }
+}
+
+/// Collect TOML string values of frontmatter keys in value, e.g. inline table
+fn toml_value_strings<'a>(
+    keys: &[String],
+    key: &str,
+    val: &'a mut toml_edit::Value,
+    strings: &mut Vec<&'a mut toml_edit::Value>,
+) {
+    match val {
+        toml_edit::Value::String(_) if is_frontmatter_key(keys, key) => strings.push(val),
+        toml_edit::Value::Array(items) => {
+            // Items have the key of array
+            for item in items.iter_mut() {
+                toml_value_strings(keys, key, item, strings);
+            }
         }
-        Err(e) => {
-            // TOML parse failed
-            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        toml_edit::Value::InlineTable(table) => {
+            for (child, val) in table.iter_mut() {
+                toml_value_strings(
+                    keys,
+                    &frontmatter::child_key(key, child.get()),
+                    val,
+                    strings,
+                );
+            }
         }
+        _ => {}
     }
 }
 
 /// Translate CommonMark
+///
+/// Long document is split into multiple requests at top level blocks.
 pub async fn translate_cmark(
     deepl: &deepl::Deepl,
+    options: &TranslateOptions,
     from_lang: deepl::Language,
     to_lang: deepl::Language,
     formality: deepl::Formality,
     cmark_text: &str,
-) -> Result<String, std::io::Error> {
-    let xml = cmark_xml::xml_from_cmark(&cmark_text, true);
-    log::trace!("XML: {}\n", xml);
-
-    // translate
-    let xml_translated = deepl
-        .translate_xml(from_lang, to_lang, formality, &xml)
-        .await
-        .unwrap();
+) -> Result<String> {
+    let xml_root = cmark_xml::xmldom_from_cmark(cmark_text, true);
+    translate_xmldom(deepl, options, from_lang, to_lang, formality, &xml_root).await
+}
+
+/// Translate XML DOM of CommonMark, and write back to CommonMark text
+async fn translate_xmldom(
+    deepl: &deepl::Deepl,
+    options: &TranslateOptions,
+    from_lang: deepl::Language,
+    to_lang: deepl::Language,
+    formality: deepl::Formality,
+    xml_root: &minidom::Element,
+) -> Result<String> {
+    let translated_blocks =
+        translate_blocks(deepl, options, from_lang, to_lang, formality, xml_root).await?;
+
+    // reassemble blocks
+    let mut translated_root = minidom::Element::bare(xml_root.name(), xml_root.ns());
+    for block in translated_blocks {
+        translated_root.append_child(block);
+    }
 
     // write back to markdown format
-    log::trace!("Translated XML: {}\n", xml);
-    let cmark_translated = cmark_xml::cmark_from_xml(&xml_translated, true).unwrap();
+    cmark_xml::cmark_from_xmldom(&translated_root, true)
+}
+
+/// Update existing translation for changed source CommonMark
+///
+/// Blocks of cmark_text are aligned with prev_cmark, which translated_cmark was translated from.
+/// Unchanged blocks keep existing translation, and only changed blocks are translated.
+#[allow(clippy::too_many_arguments)]
+pub async fn update_cmark(
+    deepl: &deepl::Deepl,
+    options: &TranslateOptions,
+    from_lang: deepl::Language,
+    to_lang: deepl::Language,
+    formality: deepl::Formality,
+    prev_cmark: &str,
+    cmark_text: &str,
+    translated_cmark: &str,
+) -> Result<String> {
+    let prev_root = cmark_xml::xmldom_from_cmark(prev_cmark, true);
+    let xml_root = cmark_xml::xmldom_from_cmark(cmark_text, true);
+    let translated_root = cmark_xml::xmldom_from_cmark(translated_cmark, true);
+    let prev_blocks = prev_root.children().collect::<Vec<_>>();
+    let blocks = xml_root.children().collect::<Vec<_>>();
+    let translated_blocks = translated_root.children().collect::<Vec<_>>();
+    if prev_blocks.len() != translated_blocks.len() {
+        // Can not tell which translated block corresponds to source
+        return Err(Error::BlockMismatch(format!(
+            "{} blocks in previous source, but {} blocks in translation",
+            prev_blocks.len(),
+            translated_blocks.len()
+        )));
+    }
+
+    // Translate only changed blocks
+    let aligned = align_blocks(&prev_blocks, &blocks);
+    let mut changed_root = minidom::Element::bare(xml_root.name(), xml_root.ns());
+    for (block, prev_index) in blocks.iter().zip(&aligned) {
+        if prev_index.is_none() {
+            changed_root.append_child((*block).clone());
+        }
+    }
+    log::info!(
+        "{} of {} blocks are changed",
+        changed_root.children().count(),
+        blocks.len()
+    );
+    let mut changed_blocks =
+        translate_blocks(deepl, options, from_lang, to_lang, formality, &changed_root)
+            .await?
+            .into_iter();
+
+    // Merge existing and new translations
+    let mut updated_root = minidom::Element::bare(xml_root.name(), xml_root.ns());
+    for prev_index in aligned {
+        if let Some(prev_index) = prev_index {
+            updated_root.append_child(translated_blocks[prev_index].clone());
+        } else if let Some(block) = changed_blocks.next() {
+            updated_root.append_child(block);
+        }
+    }
+    for block in changed_blocks {
+        updated_root.append_child(block);
+    }
+
+    cmark_xml::cmark_from_xmldom(&updated_root, true)
+}
+
+/// Align blocks by longest common subsequence
+///
+/// Returns index of the same block in prev_blocks for each of blocks, None for changed ones.
+fn align_blocks(
+    prev_blocks: &[&minidom::Element],
+    blocks: &[&minidom::Element],
+) -> Vec<Option<usize>> {
+    let (n, m) = (prev_blocks.len(), blocks.len());
+
+    // lcs[i][j] = length of LCS of prev_blocks[i..] and blocks[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if prev_blocks[i] == blocks[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut aligned = vec![None; m];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if prev_blocks[i] == blocks[j] {
+            aligned[j] = Some(i);
+            i += 1;
+            j += 1;
+        } else if lcs[i][j + 1] <= lcs[i + 1][j] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    aligned
+}
+
+/// Translate top level blocks of XML DOM, returns translated blocks in the same order
+///
+/// Blocks found in translation memory are reused, others are translated in batches.
+async fn translate_blocks(
+    deepl: &deepl::Deepl,
+    options: &TranslateOptions,
+    from_lang: deepl::Language,
+    to_lang: deepl::Language,
+    formality: deepl::Formality,
+    xml_root: &minidom::Element,
+) -> Result<Vec<minidom::Element>> {
+    let memory = options.memory.as_ref();
+    let glossary_id = deepl.glossary_id(from_lang, to_lang);
+
+    // Reuse translations in memory
+    let mut blocks = vec![];
+    let mut keys = vec![];
+    let mut untranslated_root = minidom::Element::bare(xml_root.name(), xml_root.ns());
+    for block in xml_root.children() {
+        if let Some(memory) = memory {
+            let key = memory::memory_key(
+                from_lang,
+                to_lang,
+                formality,
+                glossary_id,
+                &xml_string(block)?,
+            );
+            if let Some(translated) = memory.get(&key).and_then(|xml| xml.parse().ok()) {
+                blocks.push(Some(translated));
+                continue;
+            }
+            keys.push(key);
+        }
+        blocks.push(None);
+        untranslated_root.append_child(block.clone());
+    }
+
+    let batches = split_xmldom(&untranslated_root, options.max_request_bytes)?;
+    log::debug!(
+        "Translate {} of {} blocks in {} requests",
+        untranslated_root.children().count(),
+        blocks.len(),
+        batches.len()
+    );
+
+    // translate batches concurrently, limited by Deepl
+    let translated_batches =
+        futures::future::try_join_all(batches.iter().map(|batch| async move {
+            let xml = xml_string(batch)?;
+            log::trace!("XML: {}\n", xml);
+
+            let xml_translated = deepl
+                .translate_xml(from_lang, to_lang, formality, &xml)
+                .await?;
+            log::trace!("Translated XML: {}\n", xml_translated);
+
+            Ok::<_, Error>(xml_translated.parse::<minidom::Element>()?)
+        }))
+        .await?;
+    let translated_blocks = translated_batches
+        .iter()
+        .flat_map(|batch| batch.children().cloned())
+        .collect::<Vec<_>>();
+
+    // Store translations in memory
+    if let Some(memory) = memory {
+        if translated_blocks.len() == keys.len() {
+            for (key, block) in keys.into_iter().zip(&translated_blocks) {
+                memory.insert(key, xml_string(block)?);
+            }
+        } else {
+            log::warn!("Number of translated blocks differs from source, not stored in memory");
+        }
+    }
+
+    // Fill translated blocks in original order
+    let mut translated_blocks = translated_blocks.into_iter();
+    let mut blocks = blocks
+        .into_iter()
+        .filter_map(|block| block.or_else(|| translated_blocks.next()))
+        .collect::<Vec<_>>();
+    blocks.extend(translated_blocks);
+    Ok(blocks)
+}
+
+/// Split XML DOM into batches of top level blocks, each of them is under max_bytes
+///
+/// Size is measured after URL encoding as sent in request body, e.g. 9 bytes for
+/// 3 bytes of a CJK character. A block larger than max_bytes makes a batch by itself.
+fn split_xmldom(xml_root: &minidom::Element, max_bytes: usize) -> Result<Vec<minidom::Element>> {
+    let empty_root = || minidom::Element::bare(xml_root.name(), xml_root.ns());
+    let root_bytes = encoded_len(&xml_string(&empty_root())?);
+
+    let mut batches = vec![];
+    let mut batch = empty_root();
+    let mut batch_bytes = root_bytes;
+    for block in xml_root.children() {
+        let block_bytes = encoded_len(&xml_string(block)?);
+        if max_bytes < root_bytes + block_bytes {
+            log::warn!(
+                "Block of {} bytes exceeds max_request_bytes = {}",
+                block_bytes,
+                max_bytes
+            );
+        }
+        if max_bytes < batch_bytes + block_bytes && batch.children().next().is_some() {
+            // Start next batch
+            batches.push(std::mem::replace(&mut batch, empty_root()));
+            batch_bytes = root_bytes;
+        }
+        batch.append_child(block.clone());
+        batch_bytes += block_bytes;
+    }
+    if batch.children().next().is_some() {
+        batches.push(batch);
+    }
+    Ok(batches)
+}
+
+/// Length of text in URL encoded request body
+fn encoded_len(text: &str) -> usize {
+    form_urlencoded::byte_serialize(text.as_bytes())
+        .map(str::len)
+        .sum()
+}
+
+/// Serialize XML DOM
+fn xml_string(elm: &minidom::Element) -> Result<String> {
+    let mut buf = Vec::<u8>::new();
+    elm.write_to(&mut buf)?;
+    String::from_utf8(buf).map_err(|e| Error::Xml(e.to_string()))
+}
+
+/// Glossary term not translated as expected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlossaryViolation {
+    /// Index of top level block in source, e.g. paragraph, heading, list
+    pub block: usize,
+    /// Term found in source block
+    pub source_term: String,
+    /// Term expected, but not found in translated block
+    pub expected_term: String,
+}
+
+impl std::fmt::Display for GlossaryViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "block {}: \"{}\" is not translated as \"{}\"",
+            self.block + 1,
+            self.source_term,
+            self.expected_term
+        )
+    }
+}
+
+/// Verify glossary terms in translated CommonMark .md file
+///
+/// Frontmatter is not verified.
+pub fn verify_cmark_file<P: AsRef<std::path::Path>, S: AsRef<str>>(
+    src_path: P,
+    dst_path: P,
+    glossaries: &[(S, S)],
+) -> Result<Vec<GlossaryViolation>> {
+    let mut f = std::fs::File::open(src_path)?;
+    let (src_cmark, _) = cmark_xml::read_cmark_with_frontmatter(&mut f)?;
+    let mut f = std::fs::File::open(dst_path)?;
+    let (dst_cmark, _) = cmark_xml::read_cmark_with_frontmatter(&mut f)?;
+
+    Ok(verify_glossary(&src_cmark, &dst_cmark, glossaries))
+}
+
+/// Verify glossary terms in translated CommonMark
+///
+/// For each source term found in a block of source text, check the target term appears
+/// in the corresponding block of translated text. If blocks are split or merged in
+/// translation, they can not be paired, and the whole translated text is checked instead.
+pub fn verify_glossary<S: AsRef<str>>(
+    src_cmark: &str,
+    translated_cmark: &str,
+    glossaries: &[(S, S)],
+) -> Vec<GlossaryViolation> {
+    let src_xml = cmark_xml::xmldom_from_cmark(src_cmark, true);
+    let translated_xml = cmark_xml::xmldom_from_cmark(translated_cmark, true);
+    let translated_texts = translated_xml
+        .children()
+        .map(|block| block_text(block).to_lowercase())
+        .collect::<Vec<_>>();
+    let paired = src_xml.children().count() == translated_texts.len();
+    let whole_text = if paired {
+        String::new()
+    } else {
+        log::warn!(
+            "Number of blocks differs between source and translated text, verify whole text"
+        );
+        translated_texts.join(" ")
+    };
+
+    let mut violations = vec![];
+    for (block, src_block) in src_xml.children().enumerate() {
+        let src_text = block_text(src_block).to_lowercase();
+        let translated_text = if paired {
+            &translated_texts[block]
+        } else {
+            &whole_text
+        };
+        for (src_term, dst_term) in glossaries {
+            let (src_term, dst_term) = (src_term.as_ref().trim(), dst_term.as_ref().trim());
+            if src_term.is_empty() || dst_term.is_empty() {
+                continue;
+            }
+            if contains_term(&src_text, &src_term.to_lowercase())
+                && !translated_text.contains(&dst_term.to_lowercase())
+            {
+                violations.push(GlossaryViolation {
+                    block,
+                    source_term: src_term.to_string(),
+                    expected_term: dst_term.to_string(),
+                });
+            }
+        }
+    }
+    violations
+}
+
+/// Collect texts in XML element, including image alt text
+fn block_text(elm: &minidom::Element) -> String {
+    let mut text = String::new();
+    for node in elm.nodes() {
+        match node {
+            minidom::Node::Text(t) => text += t,
+            minidom::Node::Element(child) => {
+                if let Some(alt) = child.attr("alt") {
+                    text += alt;
+                }
+                text += &block_text(child);
+                // Separate blocks, e.g. list items
+                text.push(' ');
+            }
+        }
+    }
+    text
+}
+
+/// Test text contains term, not as a part of other ASCII word
+fn contains_term(text: &str, term: &str) -> bool {
+    text.match_indices(term).any(|(pos, _)| {
+        let before = text[..pos].chars().last();
+        let after = text[pos + term.len()..].chars().next();
+        let starts_word = term.starts_with(|c: char| c.is_ascii_alphanumeric());
+        let ends_word = term.ends_with(|c: char| c.is_ascii_alphanumeric());
+        !(starts_word && before.is_some_and(|c| c.is_ascii_alphanumeric())
+            || ends_word && after.is_some_and(|c| c.is_ascii_alphanumeric()))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frontmatter_key_patterns() {
+        let patterns = ["title", "tags", "taxonomies.*", "extra.**.og_*"].map(String::from);
+        let mut doc = r#"
+title = "Title"
+draft = "false"
+tags = ["tag1", "tag2"]
+[taxonomies]
+categories = ["category"]
+[extra]
+author = "Author"
+seo = { og_title = "OG title" }
+[[extra.links]]
+og_label = "Label"
+"#
+        .parse::<toml_edit::DocumentMut>()
+        .unwrap();
+
+        let mut values = vec![];
+        toml_strings(&patterns, "", doc.as_item_mut(), &mut values);
+        let strings = values
+            .into_iter()
+            .map(|v| v.as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            strings,
+            ["Title", "tag1", "tag2", "category", "OG title", "Label"]
+        );
+        assert!(!is_frontmatter_key(&patterns, "extra.author"));
+        assert!(is_frontmatter_key(&patterns, "extra.og_title"));
+    }
 
-    Ok(cmark_translated)
+    #[test]
+    fn align_changed_blocks() {
+        let prev = cmark_xml::xmldom_from_cmark("A\n\nB\n\nC\n\nD\n", true);
+        let new = cmark_xml::xmldom_from_cmark("A\n\nB2\n\nC\n\nE\n\nD\n", true);
+        let aligned = align_blocks(
+            &prev.children().collect::<Vec<_>>(),
+            &new.children().collect::<Vec<_>>(),
+        );
+        assert_eq!(aligned, vec![Some(0), None, Some(2), None, Some(3)]);
+    }
+
+    #[test]
+    fn split_blocks() {
+        let cmark = "# Title\n\nFirst paragraph.\n\nSecond paragraph.\n\n- item 1\n- item 2\n";
+        let xml_root = cmark_xml::xmldom_from_cmark(cmark, true);
+
+        let batches = split_xmldom(&xml_root, 1024 * 1024).unwrap();
+        assert_eq!(batches.len(), 1);
+
+        let batches = split_xmldom(&xml_root, 1).unwrap();
+        assert_eq!(batches.len(), 4);
+        let mut joined = minidom::Element::bare(xml_root.name(), xml_root.ns());
+        for batch in &batches {
+            assert!(batch.name() == "body");
+            for block in batch.children() {
+                joined.append_child(block.clone());
+            }
+        }
+        assert_eq!(cmark_xml::cmark_from_xmldom(&joined, true).unwrap(), cmark);
+
+        // CJK text is 3 times longer when URL encoded
+        let paragraph = "日本語の段落。".repeat(10);
+        let cmark = format!("{}\n\n{}\n", paragraph, paragraph);
+        let xml_root = cmark_xml::xmldom_from_cmark(&cmark, true);
+        assert!(xml_string(&xml_root).unwrap().len() < 1000);
+        let batches = split_xmldom(&xml_root, 1000).unwrap();
+        assert_eq!(batches.len(), 2);
+        for batch in &batches {
+            assert!(encoded_len(&xml_string(batch).unwrap()) <= 1000);
+        }
+
+        assert!(split_xmldom(&cmark_xml::xmldom_from_cmark("", true), 1)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn glossary_violations() {
+        let src = "# Printer setup\n\nConnect the printer.\n\nA sprinter runs fast.\n";
+        let translated = "# プリンタの設定\n\n印刷機を接続します。\n\n短距離走者は速く走ります。\n";
+        let violations = verify_glossary(src, translated, &[("printer", "プリンタ")]);
+        assert_eq!(
+            violations,
+            vec![GlossaryViolation {
+                block: 1,
+                source_term: "printer".to_string(),
+                expected_term: "プリンタ".to_string(),
+            }]
+        );
+
+        // Merged blocks, not reported against other block
+        let translated = "# プリンタの設定\n\nプリンタを接続します。短距離走者は速く走ります。\n";
+        assert!(verify_glossary(src, translated, &[("printer", "プリンタ")]).is_empty());
+        let violations = verify_glossary(src, translated, &[("runs", "走る")]);
+        assert_eq!(
+            violations,
+            vec![GlossaryViolation {
+                block: 2,
+                source_term: "runs".to_string(),
+                expected_term: "走る".to_string(),
+            }]
+        );
+
+        assert!(!contains_term("sprinter", "printer"));
+        assert!(contains_term("the printer.", "printer"));
+    }
 }
@@ -0,0 +1,704 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Mock DeepL API server for integration tests
+//!
+//! Translation is faked deterministically, text outside XML tags is upper-cased
+//! and glossary terms are replaced with target terms as they are.
+//!
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Form, Json, Router};
+use cmark_translate::{Deepl, Formality, Language, TranslateOptions};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// API key making mock server return 403 Forbidden
+pub const AUTH_FAIL_KEY: &str = "auth-fail";
+/// API key making mock server return 456 Quota exceeded
+pub const QUOTA_EXCEEDED_KEY: &str = "quota-exceeded";
+/// API key making mock server return 429 Too many requests once, then succeed
+pub const RATE_LIMITED_KEY: &str = "rate-limited";
+/// API key making mock server return 429 with Retry-After of 1 second once, then succeed
+pub const SLOW_DOWN_KEY: &str = "slow-down";
+/// Glossary name making mock server fail to get the glossary from Japanese after creation,
+/// while glossaries of other language pairs are created successfully
+pub const BROKEN_GLOSSARY_NAME: &str = "broken";
+
+type SharedState = Arc<Mutex<MockState>>;
+
+#[derive(Default)]
+struct MockState {
+    glossaries: Vec<MockGlossary>,
+    next_id: u32,
+    translate_requests: usize,
+    character_count: usize,
+    /// When 429 was returned
+    rate_limited_at: Option<Instant>,
+    /// When each translate request was accepted
+    translate_times: Vec<Instant>,
+    /// Time taken by each translate request
+    translate_delay: Duration,
+    concurrent_requests: usize,
+    peak_concurrency: usize,
+}
+
+impl MockState {
+    /// Remove glossary matching the condition, not found if none
+    #[allow(clippy::result_large_err)]
+    fn remove_glossary(
+        &mut self,
+        f: impl Fn(&MockGlossary) -> bool,
+    ) -> Result<StatusCode, Response> {
+        let len = self.glossaries.len();
+        self.glossaries.retain(|g| !f(g));
+        if self.glossaries.len() < len {
+            Ok(StatusCode::NO_CONTENT)
+        } else {
+            Err(glossary_not_found())
+        }
+    }
+}
+
+struct MockGlossary {
+    id: String,
+    name: String,
+    multilingual: bool,
+    dictionaries: Vec<MockDictionary>,
+}
+
+struct MockDictionary {
+    source_lang: String,
+    target_lang: String,
+    entries: Vec<(String, String)>,
+}
+
+impl MockGlossary {
+    fn dictionary(&self, source_lang: &str, target_lang: &str) -> Option<&MockDictionary> {
+        self.dictionaries.iter().find(|d| {
+            d.source_lang.eq_ignore_ascii_case(source_lang)
+                && d.target_lang.eq_ignore_ascii_case(target_lang)
+        })
+    }
+
+    /// v2 glossary response JSON
+    fn to_v2_json(&self) -> Value {
+        let dict = &self.dictionaries[0];
+        json!({
+            "glossary_id": self.id,
+            "name": self.name,
+            "ready": true,
+            "source_lang": dict.source_lang,
+            "target_lang": dict.target_lang,
+            "creation_time": "2024-01-01T00:00:00.000Z",
+            "entry_count": dict.entries.len(),
+        })
+    }
+
+    /// v3 glossary response JSON
+    fn to_v3_json(&self) -> Value {
+        json!({
+            "glossary_id": self.id,
+            "name": self.name,
+            "dictionaries": self.dictionaries.iter().map(MockDictionary::to_json).collect::<Vec<_>>(),
+            "creation_time": "2024-01-01T00:00:00.000Z",
+        })
+    }
+}
+
+impl MockDictionary {
+    fn from_tsv(source_lang: &str, target_lang: &str, tsv: &str) -> Self {
+        Self {
+            source_lang: source_lang.to_string(),
+            target_lang: target_lang.to_string(),
+            entries: tsv
+                .lines()
+                .filter_map(|row| row.split_once('\t'))
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        }
+    }
+
+    fn tsv(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(from, to)| format!("{}\t{}", from, to))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "source_lang": self.source_lang,
+            "target_lang": self.target_lang,
+            "entry_count": self.entries.len(),
+        })
+    }
+}
+
+/// Mock DeepL API server running on its own thread
+pub struct MockServer {
+    pub base_url: String,
+    state: SharedState,
+}
+
+impl MockServer {
+    /// Start server on a free local port
+    pub fn start() -> Self {
+        let state = SharedState::default();
+        let app = router(state.clone());
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            rt.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                axum::serve(listener, app).await.unwrap();
+            });
+        });
+
+        Self { base_url, state }
+    }
+
+    /// Write config file pointing to this server
+    pub fn write_config(&self, path: &std::path::Path, api_key: &str) {
+        std::fs::write(
+            path,
+            format!(
+                "api_key = \"{}\"\nbase_url = \"{}\"\n\n[retry]\ninitial_delay_ms = 10\n\n[glossaries]\n",
+                api_key, self.base_url
+            ),
+        )
+        .unwrap();
+    }
+
+    /// Number of translate requests received
+    pub fn translate_requests(&self) -> usize {
+        self.state.lock().unwrap().translate_requests
+    }
+
+    /// Make each translate request take delay, so that requests overlap
+    pub fn set_translate_delay(&self, delay: Duration) {
+        self.state.lock().unwrap().translate_delay = delay;
+    }
+
+    /// Max number of translate requests handled at the same time
+    pub fn peak_concurrency(&self) -> usize {
+        self.state.lock().unwrap().peak_concurrency
+    }
+
+    /// When 429 was returned, and when each translate request was accepted
+    pub fn translate_times(&self) -> (Option<Instant>, Vec<Instant>) {
+        let state = self.state.lock().unwrap();
+        (state.rate_limited_at, state.translate_times.clone())
+    }
+
+    /// IDs of registered glossaries
+    pub fn glossary_ids(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state.glossaries.iter().map(|g| g.id.clone()).collect()
+    }
+}
+
+/// Mock server and temporary directory with deepl.toml pointing to it
+///
+/// Relative paths are relative to the directory, which is removed on drop.
+pub struct TestEnv {
+    pub server: MockServer,
+    pub dir: std::path::PathBuf,
+    pub config: std::path::PathBuf,
+}
+
+impl TestEnv {
+    /// Start mock server, and create directory with config file
+    pub fn new(name: &str) -> Self {
+        Self::with_api_key(name, "dummy")
+    }
+
+    /// Same as new, with special API key, e.g. AUTH_FAIL_KEY
+    pub fn with_api_key(name: &str, api_key: &str) -> Self {
+        let server = MockServer::start();
+        let dir = std::env::temp_dir().join(format!("cmark_translate_it_{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("deepl.toml");
+        server.write_config(&config, api_key);
+        Self {
+            server,
+            dir,
+            config,
+        }
+    }
+
+    /// DeepL client configured by config file
+    pub fn deepl(&self) -> Deepl {
+        Deepl::with_config(&self.config).unwrap()
+    }
+
+    /// Path in the directory
+    pub fn path(&self, name: &str) -> std::path::PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Write file, creating parent directories, returns its path
+    pub fn write(&self, name: &str, contents: &str) -> std::path::PathBuf {
+        let path = self.path(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// Read file
+    pub fn read(&self, name: &str) -> String {
+        std::fs::read_to_string(self.path(name)).unwrap()
+    }
+
+    /// Translate src.md from English to Japanese into dst.md, returns dst.md
+    pub async fn translate_file(&self, src_body: &str) -> String {
+        let src = self.write("src.md", src_body);
+        cmark_translate::translate_cmark_file(
+            &self.deepl(),
+            &TranslateOptions::default(),
+            Language::En,
+            Language::Ja,
+            Formality::Default,
+            &src,
+            &self.path("dst.md"),
+        )
+        .await
+        .unwrap();
+        self.read("dst.md")
+    }
+
+    /// Run command line in the directory with config file, returns stdout
+    pub fn run(&self, args: &[&str]) -> String {
+        let output = self.command(args);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Run command line expected to fail, returns stderr
+    pub fn run_fail(&self, args: &[&str]) -> String {
+        let output = self.command(args);
+        assert!(!output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    }
+
+    fn command(&self, args: &[&str]) -> std::process::Output {
+        std::process::Command::new(env!("CARGO_BIN_EXE_cmark-translate"))
+            .current_dir(&self.dir)
+            .arg("--config")
+            .arg(&self.config)
+            .args(args)
+            .output()
+            .unwrap()
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn router(state: SharedState) -> Router {
+    Router::new()
+        .route("/v2/translate", post(translate))
+        .route("/v2/glossaries", post(create_glossary))
+        .route(
+            "/v2/glossaries/{id}",
+            get(get_glossary).delete(delete_glossary),
+        )
+        .route("/v2/glossaries/{id}/entries", get(glossary_entries))
+        .route("/v2/usage", get(usage))
+        .route(
+            "/v3/glossaries",
+            get(list_glossaries).post(create_multilingual_glossary),
+        )
+        .route(
+            "/v3/glossaries/{id}",
+            get(get_multilingual_glossary).delete(delete_multilingual_glossary),
+        )
+        .route(
+            "/v3/glossaries/{id}/entries",
+            get(multilingual_glossary_entries),
+        )
+        .route("/v3/glossaries/{id}/dictionaries", put(replace_dictionary))
+        .with_state(state)
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "message": message }))).into_response()
+}
+
+/// Check API key, returns error response for special keys
+#[allow(clippy::result_large_err)]
+fn authorize(headers: &HeaderMap, state: &mut MockState) -> Result<(), Response> {
+    let key = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("DeepL-Auth-Key "))
+        .unwrap_or_default();
+    match key {
+        "" | AUTH_FAIL_KEY => Err(error_response(StatusCode::FORBIDDEN, "Wrong API key")),
+        QUOTA_EXCEEDED_KEY => Err(error_response(
+            StatusCode::from_u16(456).unwrap(),
+            "Quota exceeded",
+        )),
+        RATE_LIMITED_KEY | SLOW_DOWN_KEY if state.rate_limited_at.is_none() => {
+            state.rate_limited_at = Some(Instant::now());
+            let retry_after = if key == SLOW_DOWN_KEY { "1" } else { "0" };
+            Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                [("retry-after", retry_after)],
+                Json(json!({ "message": "Too many requests" })),
+            )
+                .into_response())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn glossary_not_found() -> Response {
+    error_response(StatusCode::NOT_FOUND, "Glossary not found")
+}
+
+/// Fake translation, upper-case text and replace glossary terms
+fn fake_translate(text: &str, xml: bool, glossary: &[(String, String)]) -> String {
+    let mut translated = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if xml && (c == '<' || c == '&') {
+            // Keep tags and entities as they are
+            let close = if c == '<' { '>' } else { ';' };
+            let end = rest.find(close).map_or(rest.len(), |pos| pos + 1);
+            translated += &rest[..end];
+            rest = &rest[end..];
+        } else if let Some((from, to)) = glossary
+            .iter()
+            .find(|(from, _)| rest.starts_with(from.as_str()))
+        {
+            translated += to;
+            rest = &rest[from.len()..];
+        } else {
+            translated.extend(c.to_uppercase());
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    translated
+}
+
+async fn translate(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Form(params): Form<Vec<(String, String)>>,
+) -> Result<Json<Value>, Response> {
+    let (translations, delay) = translate_texts(&mut state.lock().unwrap(), &headers, &params)?;
+
+    // Hold the request without locking state, counting overlapping requests
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    state.lock().unwrap().concurrent_requests -= 1;
+    Ok(Json(json!({ "translations": translations })))
+}
+
+/// Translations of text parameters, and delay of response
+#[allow(clippy::result_large_err)]
+fn translate_texts(
+    state: &mut MockState,
+    headers: &HeaderMap,
+    params: &[(String, String)],
+) -> Result<(Vec<Value>, Duration), Response> {
+    authorize(headers, state)?;
+
+    let param = |key: &str| {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    let source_lang = param("source_lang").unwrap_or_default();
+    let target_lang = param("target_lang")
+        .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "target_lang is required"))?;
+    let xml = param("tag_handling") == Some("xml");
+    let glossary = if let Some(id) = param("glossary_id") {
+        let glossary = state
+            .glossaries
+            .iter()
+            .find(|g| g.id == id)
+            .ok_or_else(glossary_not_found)?;
+        glossary
+            .dictionary(source_lang, target_lang)
+            .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Glossary language mismatch"))?
+            .entries
+            .clone()
+    } else {
+        vec![]
+    };
+
+    let texts = params
+        .iter()
+        .filter(|(k, _)| k == "text")
+        .map(|(_, text)| text.as_str())
+        .collect::<Vec<_>>();
+    state.translate_requests += 1;
+    state.translate_times.push(Instant::now());
+    state.concurrent_requests += 1;
+    state.peak_concurrency = state.peak_concurrency.max(state.concurrent_requests);
+    state.character_count += texts.iter().map(|t| t.chars().count()).sum::<usize>();
+    let translations = texts
+        .iter()
+        .map(|text| {
+            json!({
+                "detected_source_language": source_lang.to_ascii_uppercase(),
+                "text": fake_translate(text, xml, &glossary),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok((translations, state.translate_delay))
+}
+
+async fn create_glossary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Form(params): Form<std::collections::HashMap<String, String>>,
+) -> Result<(StatusCode, Json<Value>), Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    let param = |key: &str| params.get(key).map(String::as_str).unwrap_or_default();
+    state.next_id += 1;
+    let glossary = MockGlossary {
+        id: format!("glossary-{}", state.next_id),
+        name: param("name").to_string(),
+        multilingual: false,
+        dictionaries: vec![MockDictionary::from_tsv(
+            param("source_lang"),
+            param("target_lang"),
+            param("entries"),
+        )],
+    };
+    let resp = glossary.to_v2_json();
+    state.glossaries.push(glossary);
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+async fn get_glossary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    let glossary = state
+        .glossaries
+        .iter()
+        .find(|g| g.id == id && !g.multilingual)
+        .ok_or_else(glossary_not_found)?;
+    if glossary.name == BROKEN_GLOSSARY_NAME
+        && glossary.dictionaries[0]
+            .source_lang
+            .eq_ignore_ascii_case("ja")
+    {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "Glossary creation failed",
+        ));
+    }
+    Ok(Json(glossary.to_v2_json()))
+}
+
+async fn delete_glossary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    // v2 API does not support multilingual glossaries
+    state.remove_glossary(|g| g.id == id && !g.multilingual)
+}
+
+async fn glossary_entries(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<([(&'static str, &'static str); 1], String), Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    state
+        .glossaries
+        .iter()
+        .find(|g| g.id == id && !g.multilingual)
+        .map(|g| {
+            (
+                [("content-type", "text/tab-separated-values")],
+                g.dictionaries[0].tsv(),
+            )
+        })
+        .ok_or_else(glossary_not_found)
+}
+
+async fn usage(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    Ok(Json(json!({
+        "character_count": state.character_count,
+        "character_limit": 500000,
+    })))
+}
+
+async fn list_glossaries(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    let glossaries = state
+        .glossaries
+        .iter()
+        .map(MockGlossary::to_v3_json)
+        .collect::<Vec<_>>();
+    Ok(Json(json!({ "glossaries": glossaries })))
+}
+
+#[derive(serde::Deserialize)]
+struct DictionaryEntries {
+    source_lang: String,
+    target_lang: String,
+    entries: String,
+}
+
+#[derive(serde::Deserialize)]
+struct MultilingualGlossaryRequest {
+    name: String,
+    dictionaries: Vec<DictionaryEntries>,
+}
+
+async fn create_multilingual_glossary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(req): Json<MultilingualGlossaryRequest>,
+) -> Result<(StatusCode, Json<Value>), Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    state.next_id += 1;
+    let glossary = MockGlossary {
+        id: format!("glossary-{}", state.next_id),
+        name: req.name,
+        multilingual: true,
+        dictionaries: req
+            .dictionaries
+            .iter()
+            .map(|d| MockDictionary::from_tsv(&d.source_lang, &d.target_lang, &d.entries))
+            .collect(),
+    };
+    let resp = glossary.to_v3_json();
+    state.glossaries.push(glossary);
+    Ok((StatusCode::CREATED, Json(resp)))
+}
+
+async fn get_multilingual_glossary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    state
+        .glossaries
+        .iter()
+        .find(|g| g.id == id)
+        .map(|g| Json(g.to_v3_json()))
+        .ok_or_else(glossary_not_found)
+}
+
+async fn delete_multilingual_glossary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    state.remove_glossary(|g| g.id == id)
+}
+
+#[derive(serde::Deserialize)]
+struct LanguagePair {
+    source_lang: String,
+    target_lang: String,
+}
+
+async fn multilingual_glossary_entries(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(pair): Query<LanguagePair>,
+) -> Result<Json<Value>, Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    let glossary = state
+        .glossaries
+        .iter()
+        .find(|g| g.id == id)
+        .ok_or_else(glossary_not_found)?;
+    let dict = glossary
+        .dictionary(&pair.source_lang, &pair.target_lang)
+        .ok_or_else(glossary_not_found)?;
+    Ok(Json(json!({
+        "dictionaries": [{
+            "source_lang": dict.source_lang,
+            "target_lang": dict.target_lang,
+            "entries": dict.tsv(),
+            "entries_format": "tsv",
+        }]
+    })))
+}
+
+async fn replace_dictionary(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(req): Json<DictionaryEntries>,
+) -> Result<Json<Value>, Response> {
+    let mut state = state.lock().unwrap();
+    authorize(&headers, &mut state)?;
+
+    let glossary = state
+        .glossaries
+        .iter_mut()
+        .find(|g| g.id == id)
+        .ok_or_else(glossary_not_found)?;
+    let dict = MockDictionary::from_tsv(&req.source_lang, &req.target_lang, &req.entries);
+    let resp = dict.to_json();
+    glossary.dictionaries.retain(|d| {
+        !(d.source_lang.eq_ignore_ascii_case(&req.source_lang)
+            && d.target_lang.eq_ignore_ascii_case(&req.target_lang))
+    });
+    glossary.dictionaries.push(dict);
+    Ok(Json(resp))
+}
@@ -0,0 +1,719 @@
+// SPDX-License-Identifier: MIT
+//!
+//! Integration tests against mock DeepL API server
+//!
+mod common;
+
+use cmark_translate::{Error, Formality, Language, TranslateOptions, TranslationMemory};
+use common::TestEnv;
+
+#[tokio::test]
+async fn translate_file_with_frontmatter() {
+    let env = TestEnv::new("translate_file");
+    let translated = env
+        .translate_file(
+            "+++\ntitle = \"Hello\"\ndraft = false\n\n[extra]\ntime = \"5 min\"\n+++\n# Hello\n\nSome *emphasis* & [link](https://example.com).\n\n<div>html</div>\n",
+        )
+        .await;
+
+    let (body, frontmatter) =
+        cmark_translate::read_cmark_with_frontmatter(&mut translated.as_bytes()).unwrap();
+    let frontmatter = frontmatter.unwrap().parse::<toml::Table>().unwrap();
+    assert_eq!(frontmatter["title"].as_str(), Some("HELLO"));
+    assert_eq!(frontmatter["draft"].as_bool(), Some(false));
+    assert_eq!(frontmatter["extra"]["time"].as_str(), Some("5 MIN"));
+    assert!(body.contains("# HELLO\n"));
+    assert!(body.contains("SOME *EMPHASIS* & [LINK](https://example.com)."));
+    assert!(body.contains("<div>html</div>"));
+}
+
+#[tokio::test]
+async fn translate_toml_frontmatter_in_place() {
+    let env = TestEnv::new("translate_toml");
+    let frontmatter = |title: &str| {
+        format!(
+            "+++\n# Page settings\ntitle =   \"{}\"  # shown in tab\ndate = 2024-01-02T03:04:05Z\nweight = 10\ntaxonomies = {{ tags = [\"a\", \"b\"] }}\n+++\n",
+            title
+        )
+    };
+
+    // Only translated value is changed
+    assert_eq!(
+        env.translate_file(&(frontmatter("Hello") + "Body.\n"))
+            .await,
+        frontmatter("HELLO") + "BODY.\n"
+    );
+}
+
+#[tokio::test]
+async fn translate_json_frontmatter() {
+    let env = TestEnv::new("translate_json");
+
+    // Only translated values are changed, and blank line after object is kept
+    assert_eq!(
+        env.translate_file(
+            "{\n  \"title\": \"Hello\",\n  \"weight\": 10,\n  \"extra\": {\"time\": \"5 min\"}\n}\n\nBody.\n"
+        )
+        .await,
+        "{\n  \"title\": \"HELLO\",\n  \"weight\": 10,\n  \"extra\": {\"time\": \"5 MIN\"}\n}\n\nBODY.\n"
+    );
+
+    // Hugo shortcode is not JSON frontmatter
+    let (body, frontmatter) = cmark_translate::read_cmark_with_frontmatter(
+        &mut "{{< note >}}\nBody.\n{{< /note >}}\n".as_bytes(),
+    )
+    .unwrap();
+    assert!(frontmatter.is_none());
+    assert!(body.starts_with("{{< note >}}"));
+}
+
+#[tokio::test]
+async fn translate_yaml_frontmatter() {
+    let env = TestEnv::new("translate_yaml");
+
+    // Delimiter and key order are kept, and translation not starting with a letter is quoted
+    assert_eq!(
+        env.translate_file(
+            "---\ntitle: Hello\ndraft: false\ndescription: First post\nextra:\n  time: 5 min\n---\n# Hello\n"
+        )
+        .await,
+        "---\ntitle: HELLO\ndraft: false\ndescription: FIRST POST\nextra:\n  time: \"5 MIN\"\n---\n# HELLO\n"
+    );
+
+    // Delimiter inside value does not close frontmatter, and "..." does
+    assert_eq!(
+        env.translate_file("---\ntitle: Hello---World\n...\n# Hello\n")
+            .await,
+        "---\ntitle: HELLO---WORLD\n---\n# HELLO\n"
+    );
+
+    // Comments, quoting, anchors and flow or block style are kept
+    assert_eq!(
+        env.translate_file(
+            "---\n# Metadata\ntitle: 'It''s new' # list\ndescription: &desc \"Say \\\"hi\\\"\"\nsummary: *desc\nextra: {time: 5 min, weight: 10}\n---\nBody.\n"
+        )
+        .await,
+        "---\n# Metadata\ntitle: 'IT''S NEW' # list\ndescription: &desc \"SAY \\\"HI\\\"\"\nsummary: *desc\nextra: {time: \"5 MIN\", weight: 10}\n---\nBODY.\n"
+    );
+    assert_eq!(
+        env.translate_file("---\ntitle: >\n  Hello\n  world\nextra:\n  time: |-\n    five\n    minutes\n---\nBody.\n")
+            .await,
+        "---\ntitle: >\n  HELLO WORLD\nextra:\n  time: |-\n    FIVE\n    MINUTES\n---\nBODY.\n"
+    );
+}
+
+#[tokio::test]
+async fn translate_footnotes() {
+    let env = TestEnv::new("translate_footnotes");
+
+    // Labels are kept, and definitions stay where they were
+    assert_eq!(
+        env.translate_file(
+            "# Notes\n\nSome text[^note] and more[^2].\n\n[^note]: Footnote body.\n\nNext paragraph.\n\n[^2]: Second note.\n"
+        )
+        .await,
+        "# NOTES\n\nSOME TEXT[^note] AND MORE[^2].\n\n[^note]: FOOTNOTE BODY.\n\nNEXT PARAGRAPH.\n\n[^2]: SECOND NOTE.\n"
+    );
+}
+
+#[tokio::test]
+async fn translate_with_glossary() {
+    let env = TestEnv::new("translate_glossary");
+    let mut deepl = env.deepl();
+
+    let glossaries = [("printer", "プリンタ")];
+    let glossary = deepl
+        .register_glossaries("test", Language::En, Language::Ja, &glossaries)
+        .await
+        .unwrap();
+    deepl
+        .update_glossary_id(Language::En, Language::Ja, &glossary.glossary_id)
+        .unwrap();
+
+    let src = "Connect the printer.\n";
+    let translated = cmark_translate::translate_cmark(
+        &deepl,
+        &TranslateOptions::default(),
+        Language::En,
+        Language::Ja,
+        Formality::Default,
+        src,
+    )
+    .await
+    .unwrap();
+    assert_eq!(translated, "CONNECT THE プリンタ.\n");
+    assert!(cmark_translate::verify_glossary(src, &translated, &glossaries).is_empty());
+
+    // Glossary is not used for other language pair
+    let translated = deepl
+        .translate(Language::En, Language::De, Formality::Default, "printer")
+        .await
+        .unwrap();
+    assert_eq!(translated, "PRINTER");
+}
+
+#[tokio::test]
+async fn translate_long_document_in_chunks() {
+    let env = TestEnv::new("chunks");
+    let deepl = env.deepl();
+    let options = TranslateOptions {
+        max_request_bytes: 300,
+        ..Default::default()
+    };
+
+    let src = (1..=20)
+        .map(|n| format!("Paragraph {} of long document.\n", n))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let translated = cmark_translate::translate_cmark(
+        &deepl,
+        &options,
+        Language::En,
+        Language::Ja,
+        Formality::Default,
+        &src,
+    )
+    .await
+    .unwrap();
+    assert_eq!(translated, src.to_uppercase());
+    assert!(1 < env.server.translate_requests());
+}
+
+#[tokio::test]
+async fn translate_files_concurrently() {
+    let env = TestEnv::new("concurrent");
+    let mut deepl = env.deepl();
+    let options = TranslateOptions {
+        max_request_bytes: 200,
+        ..Default::default()
+    };
+    deepl.set_concurrency(3);
+    env.server
+        .set_translate_delay(std::time::Duration::from_millis(50));
+
+    let files = (1..=3)
+        .map(|n| {
+            let body = (1..=5)
+                .map(|p| format!("File {} paragraph {}.\n", n, p))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let src = env.write(&format!("src{}.md", n), &body);
+            (src, env.path(&format!("dst{}.md", n)))
+        })
+        .collect::<Vec<_>>();
+
+    let results = cmark_translate::translate_cmark_files(
+        &deepl,
+        &options,
+        Language::En,
+        Language::Ja,
+        Formality::Default,
+        &files,
+    )
+    .await;
+    assert!(results.iter().all(|r| r.is_ok()));
+    for (src, dst) in &files {
+        let src_body = std::fs::read_to_string(src).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dst).unwrap(),
+            src_body.to_uppercase()
+        );
+    }
+
+    // Requests overlap, but not more than the limit
+    let peak = env.server.peak_concurrency();
+    assert!(1 < peak && peak <= 3, "peak concurrency {}", peak);
+}
+
+#[tokio::test]
+async fn rate_limit_delays_all_workers() {
+    let env = TestEnv::with_api_key("slow_down", common::SLOW_DOWN_KEY);
+    let mut deepl = env.deepl();
+    let options = TranslateOptions {
+        max_request_bytes: 200,
+        ..Default::default()
+    };
+    deepl.set_concurrency(3);
+    let delay = std::time::Duration::from_millis(200);
+    env.server.set_translate_delay(delay);
+
+    let files = (1..=3)
+        .map(|n| {
+            let body = (1..=5)
+                .map(|p| format!("File {} paragraph {}.\n", n, p))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let src = env.write(&format!("src{}.md", n), &body);
+            (src, env.path(&format!("dst{}.md", n)))
+        })
+        .collect::<Vec<_>>();
+    let results = cmark_translate::translate_cmark_files(
+        &deepl,
+        &options,
+        Language::En,
+        Language::Ja,
+        Formality::Default,
+        &files,
+    )
+    .await;
+    assert!(results.iter().all(|r| r.is_ok()));
+
+    // Requests already sent with the rate limited one go through, but others sent after
+    // the in-flight ones finish wait for Retry-After of 1 second
+    let (rate_limited_at, times) = env.server.translate_times();
+    let rate_limited_at = rate_limited_at.unwrap();
+    let elapsed = times
+        .iter()
+        .map(|t| t.saturating_duration_since(rate_limited_at))
+        .collect::<Vec<_>>();
+    assert!(
+        elapsed
+            .iter()
+            .all(|e| *e < delay / 2 || *e >= std::time::Duration::from_secs(1)),
+        "{:?}",
+        elapsed
+    );
+    assert!(elapsed
+        .iter()
+        .any(|e| *e >= std::time::Duration::from_secs(1)));
+}
+
+#[tokio::test]
+async fn reuse_translation_memory() {
+    let env = TestEnv::new("memory");
+    let memory_path = env.path("memory.json");
+
+    let translate = |src: &'static str| {
+        let deepl = env.deepl();
+        let options = TranslateOptions {
+            memory: Some(TranslationMemory::open(&memory_path).unwrap()),
+            ..Default::default()
+        };
+        async move {
+            let translated = cmark_translate::translate_cmark(
+                &deepl,
+                &options,
+                Language::En,
+                Language::Ja,
+                Formality::Default,
+                src,
+            )
+            .await
+            .unwrap();
+            let memory = options.memory.as_ref().unwrap();
+            memory.save().unwrap();
+            (translated, memory.hits(), memory.misses())
+        }
+    };
+
+    let (translated, hits, misses) = translate("# Title\n\nFirst.\n\nSecond.\n").await;
+    assert_eq!(translated, "# TITLE\n\nFIRST.\n\nSECOND.\n");
+    assert_eq!((hits, misses), (0, 3));
+    assert_eq!(env.server.translate_requests(), 1);
+
+    // Only edited paragraph is translated
+    let (translated, hits, misses) = translate("# Title\n\nFirst edited.\n\nSecond.\n").await;
+    assert_eq!(translated, "# TITLE\n\nFIRST EDITED.\n\nSECOND.\n");
+    assert_eq!((hits, misses), (2, 1));
+    assert_eq!(env.server.translate_requests(), 2);
+
+    // Nothing is translated
+    let (_, hits, misses) = translate("# Title\n\nFirst edited.\n\nSecond.\n").await;
+    assert_eq!((hits, misses), (3, 0));
+    assert_eq!(env.server.translate_requests(), 2);
+}
+
+#[tokio::test]
+async fn update_keeps_post_edits() {
+    let env = TestEnv::new("update");
+    let deepl = env.deepl();
+
+    let prev_src = env.write(
+        "prev.md",
+        "+++\ntitle = \"Title\"\n+++\nFirst.\n\nSecond.\n\nThird.\n",
+    );
+    let src = env.write(
+        "src.md",
+        "+++\ntitle = \"Title\"\n+++\nFirst.\n\nSecond edited.\n\nInserted.\n\nThird.\n",
+    );
+    // Translation of prev_src, manually edited
+    let dst = env.write(
+        "dst.md",
+        "+++\ntitle = \"Edited title\"\n+++\nFIRST, edited by hand.\n\nSECOND.\n\nTHIRD.\n",
+    );
+
+    cmark_translate::update_cmark_file(
+        &deepl,
+        &TranslateOptions::default(),
+        Language::En,
+        Language::Ja,
+        Formality::Default,
+        &prev_src,
+        &src,
+        &dst,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        env.read("dst.md"),
+        "+++\ntitle = \"Edited title\"\n+++\nFIRST, edited by hand.\n\nSECOND EDITED.\n\nINSERTED.\n\nTHIRD.\n"
+    );
+
+    // Blocks of translation must correspond to previous source
+    env.write("dst.md", "Merged.\n");
+    assert!(matches!(
+        cmark_translate::update_cmark_file(
+            &deepl,
+            &TranslateOptions::default(),
+            Language::En,
+            Language::Ja,
+            Formality::Default,
+            &prev_src,
+            &src,
+            &dst,
+        )
+        .await,
+        Err(Error::BlockMismatch(_))
+    ));
+}
+
+#[tokio::test]
+async fn glossary_management() {
+    let env = TestEnv::new("glossary_management");
+    let deepl = env.deepl();
+
+    // v2 glossary
+    let glossary = deepl
+        .register_glossaries(
+            "v2",
+            Language::En,
+            Language::Ja,
+            &[("printer", "プリンタ"), ("scanner", "スキャナ")],
+        )
+        .await
+        .unwrap();
+    assert_eq!(glossary.entry_count, 2);
+    let glossary = deepl
+        .wait_glossary_ready(&glossary.glossary_id, std::time::Duration::from_secs(1))
+        .await
+        .unwrap();
+    assert!(glossary.ready);
+    assert_eq!(
+        deepl.glossary_entries(&glossary.glossary_id).await.unwrap(),
+        vec![
+            ("printer".to_string(), "プリンタ".to_string()),
+            ("scanner".to_string(), "スキャナ".to_string()),
+        ]
+    );
+
+    // v3 multilingual glossary
+    let entries = [("printer", "Drucker")];
+    let multilingual = deepl
+        .register_multilingual_glossary("v3", &[(Language::En, Language::De, &entries[..])])
+        .await
+        .unwrap();
+    let dict = deepl
+        .replace_glossary_dictionary(
+            &multilingual.glossary_id,
+            Language::En,
+            Language::Ja,
+            &[("printer", "プリンタ")],
+        )
+        .await
+        .unwrap();
+    assert_eq!(dict.entry_count, 1);
+    let multilingual = deepl
+        .get_multilingual_glossary(&multilingual.glossary_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        multilingual.language_pairs(),
+        vec![("en", "de"), ("en", "ja")]
+    );
+    assert_eq!(
+        deepl
+            .multilingual_glossary_entries(&multilingual.glossary_id, Language::En, Language::De)
+            .await
+            .unwrap(),
+        vec![("printer".to_string(), "Drucker".to_string())]
+    );
+
+    // List and delete
+    let listed = deepl.list_glossaries().await.unwrap();
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0].source_lang, "en");
+    assert_eq!(listed[0].target_lang, "ja");
+    deepl.remove_glossary(&glossary.glossary_id).await.unwrap();
+    assert_eq!(
+        env.server.glossary_ids(),
+        vec![multilingual.glossary_id.clone()]
+    );
+
+    // Multilingual glossary can be deleted only by v3 API
+    assert!(deepl
+        .remove_glossary(&multilingual.glossary_id)
+        .await
+        .is_err());
+    deepl
+        .delete_multilingual_glossary(&multilingual.glossary_id)
+        .await
+        .unwrap();
+    assert!(env.server.glossary_ids().is_empty());
+}
+
+#[tokio::test]
+async fn error_responses() {
+    let env = TestEnv::with_api_key("auth_fail", common::AUTH_FAIL_KEY);
+    assert!(matches!(env.deepl().get_usage().await, Err(Error::Auth(_))));
+
+    let env = TestEnv::with_api_key("quota_exceeded", common::QUOTA_EXCEEDED_KEY);
+    assert!(matches!(
+        env.deepl()
+            .translate(Language::En, Language::Ja, Formality::Default, "Hello")
+            .await,
+        Err(Error::QuotaExceeded(_))
+    ));
+
+    // Retried after 429
+    let env = TestEnv::with_api_key("rate_limited", common::RATE_LIMITED_KEY);
+    let deepl = env.deepl();
+    let translated = deepl
+        .translate(Language::En, Language::Ja, Formality::Default, "Hello")
+        .await
+        .unwrap();
+    assert_eq!(translated, "HELLO");
+    assert_eq!(deepl.get_usage().await.unwrap(), 5);
+}
+
+#[test]
+fn command_line_glossary_sync_and_translate() {
+    let env = TestEnv::new("cli");
+    env.write("glossary.csv", "en,ja\nprinter,プリンタ\n");
+    env.write("src.md", "# Setup\n\nConnect the printer.\n");
+    let sync = [
+        "glossary",
+        "sync",
+        "--name",
+        "docs",
+        "--from",
+        "en",
+        "--to",
+        "ja",
+        "glossary.csv",
+    ];
+
+    env.run(&["glossary", "check", "glossary.csv"]);
+    env.run(&sync);
+    let ids = env.server.glossary_ids();
+    assert_eq!(ids.len(), 1);
+    assert!(env
+        .read("deepl.toml")
+        .contains(&format!("en_ja = \"{}\"", ids[0])));
+
+    let report = env.run(&[
+        "translate",
+        "--from",
+        "en",
+        "--to",
+        "ja",
+        "--verify-glossary",
+        "glossary.csv",
+        "src.md",
+        "dst.md",
+    ]);
+    assert_eq!(report, "");
+    assert_eq!(env.read("dst.md"), "# SETUP\n\nCONNECT THE プリンタ.\n");
+
+    // Sync again replaces old glossary
+    env.run(&sync);
+    let new_ids = env.server.glossary_ids();
+    assert_eq!(new_ids.len(), 1);
+    assert_ne!(new_ids, ids);
+}
+
+#[test]
+fn command_line_glossary_sync_cleanup() {
+    let env = TestEnv::new("cli_sync_cleanup");
+    env.write("glossary.csv", "en,ja\nprinter,プリンタ\n");
+    let sync = |name: &'static str, multilingual: bool| {
+        let mut args = vec!["glossary", "sync", "--name", name, "glossary.csv"];
+        if multilingual {
+            args.push("--multilingual");
+        }
+        args
+    };
+
+    // Old multilingual glossary is deleted after switching to v2 glossary
+    env.run(&sync("docs", true));
+    let ids = env.server.glossary_ids();
+    assert_eq!(ids.len(), 1);
+    env.run(&sync("docs", false));
+    let new_ids = env.server.glossary_ids();
+    assert!(!new_ids.contains(&ids[0]));
+
+    // When a later pair fails, glossaries of all pairs are not left on the account,
+    // and config still points to the old ones
+    let config = env.read("deepl.toml");
+    env.run_fail(&sync(common::BROKEN_GLOSSARY_NAME, false));
+    assert_eq!(env.server.glossary_ids(), new_ids);
+    assert_eq!(env.read("deepl.toml"), config);
+}
+
+#[test]
+fn command_line_translate_directory() {
+    let env = TestEnv::new("dir");
+    env.write("docs/intro.md", "# Intro\n");
+    env.write("docs/intro.ja.md", "# 既存の翻訳\n");
+    env.write("docs/guide/setup.md", "Setup.\n");
+    env.write("docs/guide/image.png", "PNG");
+
+    // Docusaurus layout
+    env.run(&[
+        "translate",
+        "--from",
+        "en",
+        "--to",
+        "ja",
+        "docs",
+        "i18n/{lang}/current/{path}",
+    ]);
+    assert_eq!(env.read("i18n/ja/current/intro.md"), "# INTRO\n");
+    assert_eq!(env.read("i18n/ja/current/guide/setup.md"), "SETUP.\n");
+    assert_eq!(env.read("i18n/ja/current/guide/image.png"), "PNG");
+    assert!(!env.path("i18n/ja/current/intro.ja.md").exists());
+
+    // Zola, Hugo layout by glob pattern, assets are shared
+    env.run(&[
+        "translate",
+        "--from",
+        "en",
+        "--to",
+        "ja",
+        "docs/**/*",
+        "docs/{dir}/{stem}.{lang}.{ext}",
+    ]);
+    assert_eq!(env.read("docs/intro.ja.md"), "# INTRO\n");
+    assert_eq!(env.read("docs/guide/setup.ja.md"), "SETUP.\n");
+    assert!(!env.path("docs/guide/image.ja.png").exists());
+}
+
+#[test]
+fn command_line_previous_requires_single_file() {
+    let env = TestEnv::new("previous");
+    env.write("docs/intro.md", "# Intro\n");
+    env.write("docs/image.png", "PNG");
+    env.write("old/intro.md", "# Old intro\n");
+
+    // Previous version can't be matched with files in directory
+    let stderr = env.run_fail(&[
+        "translate",
+        "--from",
+        "en",
+        "--to",
+        "ja",
+        "--previous",
+        "old/intro.md",
+        "docs",
+        "i18n/{lang}/{path}",
+    ]);
+    assert!(stderr.contains("--previous"), "{}", stderr);
+    assert!(!env.path("i18n").exists());
+    assert_eq!(env.server.translate_requests(), 0);
+}
+
+#[test]
+fn command_line_translate_multiple_languages() {
+    let env = TestEnv::new("langs");
+    env.write("docs/intro.md", "# Intro\n");
+    env.write("docs/intro.ja.md", "# 既存の翻訳\n");
+    env.write("docs/guide/setup.md", "Setup.\n");
+    env.write("docs/guide/image.png", "PNG");
+
+    // Each file is translated into every language, and assets are copied for each
+    env.run(&[
+        "translate",
+        "--from",
+        "en",
+        "--to",
+        "ja,de",
+        "docs",
+        "i18n/{lang}/current/{path}",
+    ]);
+    assert_eq!(env.server.translate_requests(), 4);
+    for lang in ["ja", "de"] {
+        let current = format!("i18n/{}/current", lang);
+        assert_eq!(env.read(&format!("{}/intro.md", current)), "# INTRO\n");
+        assert_eq!(env.read(&format!("{}/guide/setup.md", current)), "SETUP.\n");
+        assert_eq!(env.read(&format!("{}/guide/image.png", current)), "PNG");
+        assert!(!env.path(&format!("{}/intro.ja.md", current)).exists());
+    }
+}
+
+#[test]
+fn command_line_sync_project() {
+    let env = TestEnv::new("sync");
+    env.write(
+        "content/index.md",
+        "+++\ntitle = \"Home\"\ndescription = \"Top page\"\n[extra]\nsummary = \"Welcome\"\n+++\nHello.\n",
+    );
+    env.write("content/drafts/wip.md", "Draft.\n");
+    env.write(
+        "cmark-translate.toml",
+        r#"
+from = "en"
+sources = ["content"]
+exclude = ["content/drafts/*"]
+output = "content/{dir}/{stem}.{lang}.{ext}"
+frontmatter_keys = ["title", "extra.summary"]
+
+[targets]
+ja = { formality = "formal" }
+"#,
+    );
+
+    env.run(&["sync"]);
+    let translated = env.read("content/index.ja.md");
+    assert!(translated.contains("title = \"HOME\""), "{}", translated);
+    assert!(
+        translated.contains("description = \"Top page\""),
+        "{}",
+        translated
+    );
+    assert!(
+        translated.contains("summary = \"WELCOME\""),
+        "{}",
+        translated
+    );
+    assert!(translated.ends_with("+++\nHELLO.\n"), "{}", translated);
+    assert!(!env.path("content/drafts/wip.ja.md").exists());
+    let requests = env.server.translate_requests();
+
+    // Up to date, nothing translated
+    env.run(&["sync", "--project", "cmark-translate.toml"]);
+    assert_eq!(env.server.translate_requests(), requests);
+}
+
+#[test]
+fn command_line_frontmatter_keys() {
+    let env = TestEnv::new("keys");
+    env.write(
+        "src.md",
+        "---\ntitle: Hello\nsummary: Short\ntags:\n- news\n- release\nextra:\n  seo:\n    og_title: Share\n  slug: hello\n---\nBody.\n",
+    );
+
+    env.run(&[
+        "translate",
+        "--from",
+        "en",
+        "--to",
+        "ja",
+        "--frontmatter-keys",
+        "summary,tags,extra.**.og_*",
+        "src.md",
+        "dst.md",
+    ]);
+    assert_eq!(
+        env.read("dst.md"),
+        "---\ntitle: Hello\nsummary: SHORT\ntags:\n- NEWS\n- RELEASE\nextra:\n  seo:\n    og_title: SHARE\n  slug: hello\n---\nBODY.\n"
+    );
+}